@@ -10,7 +10,7 @@
 //!
 //! # Examples
 //!
-//! ```
+//! ```no_run
 //! extern crate gpiochip as gpio;
 //!
 //! /// Print information about first gpiochip
@@ -26,14 +26,14 @@
 //!     for i in 0..chip.lines {
 //!         let info = chip.info(i).unwrap();
 //!
-//!         println!(" GPIO {:?}: {:?}", info.gpio, info.name);
-//!         println!("     Consumer: {:?}", info.consumer);
+//!         println!(" GPIO {:?}: {}", info.gpio, info.name.as_deref().unwrap_or("unnamed"));
+//!         println!("     Consumer: {}", info.consumer.as_deref().unwrap_or("unused"));
 //!         println!("     Flags: {:?}", info.flags);
 //!     }
 //! }
 //! ```
 //!
-//! ```
+//! ```no_run
 //! extern crate gpiochip as gpio;
 //!
 //! /// Simple get/set example
@@ -47,9 +47,11 @@
 //! }
 //! ```
 //!
-//! ```
+//! ```no_run
 //! extern crate gpiochip as gpio;
 //!
+//! use std::time::Duration;
+//!
 //! /// GPIO events
 //! fn main() {
 //!     let chip = gpio::GpioChip::new("/dev/gpiochip0").unwrap();
@@ -57,16 +59,9 @@
 //!     let gpio_a = chip.request_event("gpioA", 0, gpio::RequestFlags::INPUT, gpio::EventRequestFlags::BOTH_EDGES).unwrap();
 //!     let gpio_b = chip.request_event("gpioB", 1, gpio::RequestFlags::INPUT, gpio::EventRequestFlags::BOTH_EDGES).unwrap();
 //!
-//!     let bitmap = gpio::wait_for_event(&[&gpio_a, &gpio_b], 1000).unwrap();
-//!
-//!     if bitmap & 0b01 == 0b01 {
-//!         let event = gpio_a.read().unwrap();
-//!         println!("gpioA: event @ {:?} - {:?}", event.timestamp, event.id);
-//!     }
-//!
-//!     if bitmap & 0b10 == 0b10 {
-//!         let event = gpio_b.read().unwrap();
-//!         println!("gpioB: event @ {:?} - {:?}", event.timestamp, event.id);
+//!     for handle in gpio::wait_ready(&[&gpio_a, &gpio_b], Some(Duration::from_secs(1))).unwrap() {
+//!         let event = handle.read().unwrap();
+//!         println!("gpio {}: event @ {:?} - {:?}", handle.gpio, event.timestamp, event.id);
 //!     }
 //! }
 //! ```
@@ -74,13 +69,36 @@
 #[macro_use] extern crate nix;
 #[macro_use] extern crate bitflags;
 extern crate libc;
+#[cfg(feature = "async-std")] extern crate async_io;
+#[cfg(any(feature = "async-std", feature = "tokio"))] extern crate futures_core;
+#[cfg(feature = "tokio")] extern crate tokio;
+#[cfg(feature = "mio")] extern crate mio;
+#[cfg(feature = "embedded-hal")] extern crate embedded_hal;
+#[cfg(feature = "serde")] extern crate serde;
 
 use std::io;
 use std::os::unix::io::RawFd;
 use std::os::unix::io::IntoRawFd;
 use std::os::unix::io::FromRawFd;
 use std::os::unix::io::AsRawFd;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::BorrowedFd;
+use std::os::unix::io::OwnedFd;
+use std::convert::TryFrom;
 use std::ffi::CStr;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::thread;
 
 bitflags! {
     /// bitflag describing the current gpio mode
@@ -95,6 +113,12 @@ bitflags! {
         const OPEN_DRAIN  = 0b00001000;
         /// The GPIO is open-source
         const OPEN_SOURCE = 0b00010000;
+        /// The GPIO has its internal pull-up enabled
+        const BIAS_PULL_UP   = 0b00100000;
+        /// The GPIO has its internal pull-down enabled
+        const BIAS_PULL_DOWN = 0b01000000;
+        /// The GPIO has its internal pull-up and pull-down disabled
+        const BIAS_DISABLE   = 0b10000000;
     }
 }
 
@@ -111,6 +135,12 @@ bitflags! {
         const OPEN_DRAIN  = 0b00001000;
         /// Request open-source mode
         const OPEN_SOURCE = 0b00010000;
+        /// Request enabling the internal pull-up
+        const BIAS_PULL_UP   = 0b00100000;
+        /// Request enabling the internal pull-down
+        const BIAS_PULL_DOWN = 0b01000000;
+        /// Request disabling the internal pull-up and pull-down
+        const BIAS_DISABLE   = 0b10000000;
     }
 }
 
@@ -126,32 +156,513 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// bitflag describing the configuration of a line using the v2 API
+    pub struct LineFlags: u64 {
+        /// The line is in use (only reported by line info)
+        const USED                 = 1 << 0;
+        /// The line is active-low
+        const ACTIVE_LOW           = 1 << 1;
+        /// The line is an input
+        const INPUT                = 1 << 2;
+        /// The line is an output
+        const OUTPUT               = 1 << 3;
+        /// Generate events on rising edges
+        const EDGE_RISING          = 1 << 4;
+        /// Generate events on falling edges
+        const EDGE_FALLING         = 1 << 5;
+        /// The line is open-drain
+        const OPEN_DRAIN           = 1 << 6;
+        /// The line is open-source
+        const OPEN_SOURCE          = 1 << 7;
+        /// Enable the internal pull-up
+        const BIAS_PULL_UP         = 1 << 8;
+        /// Enable the internal pull-down
+        const BIAS_PULL_DOWN       = 1 << 9;
+        /// Disable the internal pull-up and pull-down
+        const BIAS_DISABLED        = 1 << 10;
+        /// Timestamp events with `CLOCK_REALTIME`
+        const EVENT_CLOCK_REALTIME = 1 << 11;
+        /// Timestamp events with the hardware timestamp engine
+        const EVENT_CLOCK_HTE      = 1 << 12;
+    }
+}
+
+impl From<RequestFlags> for LineFlags {
+    fn from(flags: RequestFlags) -> LineFlags {
+        let table = [
+            (RequestFlags::INPUT, LineFlags::INPUT),
+            (RequestFlags::OUTPUT, LineFlags::OUTPUT),
+            (RequestFlags::ACTIVE_LOW, LineFlags::ACTIVE_LOW),
+            (RequestFlags::OPEN_DRAIN, LineFlags::OPEN_DRAIN),
+            (RequestFlags::OPEN_SOURCE, LineFlags::OPEN_SOURCE),
+            (RequestFlags::BIAS_PULL_UP, LineFlags::BIAS_PULL_UP),
+            (RequestFlags::BIAS_PULL_DOWN, LineFlags::BIAS_PULL_DOWN),
+            (RequestFlags::BIAS_DISABLE, LineFlags::BIAS_DISABLED),
+        ];
+
+        table.iter().filter(|&&(v1, _)| flags.contains(v1)).fold(LineFlags::empty(), |acc, &(_, v2)| acc | v2)
+    }
+}
+
+impl From<EventRequestFlags> for LineFlags {
+    fn from(flags: EventRequestFlags) -> LineFlags {
+        let mut result = LineFlags::empty();
+
+        if flags.contains(EventRequestFlags::RISING_EDGE) {
+            result |= LineFlags::EDGE_RISING;
+        }
+        if flags.contains(EventRequestFlags::FALLING_EDGE) {
+            result |= LineFlags::EDGE_FALLING;
+        }
+
+        result
+    }
+}
+
+/// Collect the names of the single bit flags set in `$flags`
+macro_rules! flag_names {
+    ($flags:expr, $ty:ident: $($flag:ident),*) => {
+        [$(($ty::$flag, stringify!($flag))),*].iter()
+            .filter(|&&(flag, _)| $flags.contains(flag))
+            .map(|&(_, name)| name)
+            .collect()
+    };
+}
+
+/// Implement serde for a flags type as a list of flag names
+///
+/// Bits without a known flag are appended as hex string, e.g. `"0x100"`.
+#[cfg(feature = "serde")]
+macro_rules! serde_flags {
+    ($ty:ident: $($flag:ident),*) => {
+        impl serde::Serialize for $ty {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut names: Vec<String> = self.names().into_iter().map(String::from).collect();
+
+                let unknown = self.bits & !$ty::all().bits;
+                if unknown != 0 {
+                    names.push(format!("{:#x}", unknown));
+                }
+
+                serializer.collect_seq(names)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $ty {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<$ty, D::Error> {
+                let mut flags = $ty::empty();
+
+                for name in Vec::<String>::deserialize(deserializer)? {
+                    flags |= match name.as_str() {
+                        $(stringify!($flag) => $ty::$flag,)*
+                        _ if name.starts_with("0x") => match u32::from_str_radix(&name[2..], 16) {
+                            Ok(bits) => $ty { bits },
+                            Err(_) => return Err(serde::de::Error::custom(format!("invalid flag bits {:?}", name))),
+                        },
+                        _ => return Err(serde::de::Error::unknown_variant(&name, &[$(stringify!($flag)),*])),
+                    };
+                }
+
+                Ok(flags)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "serde")]
+serde_flags!(Flags: KERNEL, OUTPUT, ACTIVE_LOW, OPEN_DRAIN, OPEN_SOURCE, BIAS_PULL_UP, BIAS_PULL_DOWN, BIAS_DISABLE);
+#[cfg(feature = "serde")]
+serde_flags!(RequestFlags: INPUT, OUTPUT, ACTIVE_LOW, OPEN_DRAIN, OPEN_SOURCE, BIAS_PULL_UP, BIAS_PULL_DOWN, BIAS_DISABLE);
+#[cfg(feature = "serde")]
+serde_flags!(EventRequestFlags: RISING_EDGE, FALLING_EDGE);
+
+impl Flags {
+    /// Create flags from `bits`, keeping bits without a known flag
+    ///
+    /// Unlike `from_bits()` and `from_bits_truncate()` this does not lose
+    /// flags added by newer kernels.
+    pub const fn from_bits_retain(bits: u32) -> Flags {
+        Flags { bits }
+    }
+
+    /// Get the bits set, that have no known flag
+    pub fn unknown_bits(&self) -> u32 {
+        self.bits & !Flags::all().bits
+    }
+
+    /// Get the names of the set flags, e.g. `["OUTPUT", "ACTIVE_LOW"]`
+    pub fn names(&self) -> Vec<&'static str> {
+        flag_names!(self, Flags: KERNEL, OUTPUT, ACTIVE_LOW, OPEN_DRAIN, OPEN_SOURCE, BIAS_PULL_UP, BIAS_PULL_DOWN, BIAS_DISABLE)
+    }
+
+    /// Check if the gpio is configured as output
+    pub fn is_output(&self) -> bool {
+        self.contains(Flags::OUTPUT)
+    }
+
+    /// Check if the gpio is configured as input
+    ///
+    /// The kernel has no input flag, so this is true for all non-outputs.
+    pub fn is_input(&self) -> bool {
+        !self.is_output()
+    }
+
+    /// Check if the gpio is active-low
+    pub fn is_active_low(&self) -> bool {
+        self.contains(Flags::ACTIVE_LOW)
+    }
+
+    /// Check if the gpio is configured as open-drain
+    pub fn is_open_drain(&self) -> bool {
+        self.contains(Flags::OPEN_DRAIN)
+    }
+
+    /// Check if the gpio is configured as open-source
+    pub fn is_open_source(&self) -> bool {
+        self.contains(Flags::OPEN_SOURCE)
+    }
+
+    /// Check if the gpio is in use by the kernel or a process
+    pub fn is_kernel_used(&self) -> bool {
+        self.contains(Flags::KERNEL)
+    }
+}
+
+impl RequestFlags {
+    /// Get the names of the set flags, e.g. `["OUTPUT", "ACTIVE_LOW"]`
+    pub fn names(&self) -> Vec<&'static str> {
+        flag_names!(self, RequestFlags: INPUT, OUTPUT, ACTIVE_LOW, OPEN_DRAIN, OPEN_SOURCE, BIAS_PULL_UP, BIAS_PULL_DOWN, BIAS_DISABLE)
+    }
+}
+
+impl EventRequestFlags {
+    /// Get the names of the set flags
+    ///
+    /// `BOTH_EDGES` is reported as `["RISING_EDGE", "FALLING_EDGE"]`.
+    pub fn names(&self) -> Vec<&'static str> {
+        flag_names!(self, EventRequestFlags: RISING_EDGE, FALLING_EDGE)
+    }
+}
+
 /// Data returned by `GpioChip::info()`
-#[derive(Clone)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LineInfo {
     /// The GPIO number
     pub gpio: u32,
-    /// The GPIO name
-    pub name: String,
-    /// The GPIO consumer name
-    pub consumer: String,
+    /// The GPIO name or `None` for unnamed gpios
+    pub name: Option<String>,
+    /// The GPIO consumer name or `None` if the kernel reports none
+    pub consumer: Option<String>,
     /// The GPIO flags
     pub flags: Flags,
 }
 
+impl LineInfo {
+    /// Get the flags as reported by the kernel, including unknown bits
+    ///
+    /// For info read with the v2 API, only flags with a v1 equivalent are
+    /// included.
+    pub fn raw_flags(&self) -> u32 {
+        self.flags.bits()
+    }
+
+    fn from_raw(info: &ioctl::gpioline_info) -> LineInfo {
+        let name = unsafe {CStr::from_ptr(info.name.as_ptr())}.to_string_lossy().into_owned();
+        let consumer = unsafe {CStr::from_ptr(info.consumer.as_ptr())}.to_string_lossy().into_owned();
+        let flags = Flags::from_bits_retain(info.flags);
+        LineInfo {gpio: info.line_offset, name: Some(name).filter(|s| !s.is_empty()), consumer: Some(consumer).filter(|s| !s.is_empty()), flags}
+    }
+
+    fn from_raw_v2(info: &ioctl::v2::gpio_v2_line_info) -> LineInfo {
+        const FLAGS: &[(LineFlags, Flags)] = &[
+            (LineFlags::USED, Flags::KERNEL),
+            (LineFlags::OUTPUT, Flags::OUTPUT),
+            (LineFlags::ACTIVE_LOW, Flags::ACTIVE_LOW),
+            (LineFlags::OPEN_DRAIN, Flags::OPEN_DRAIN),
+            (LineFlags::OPEN_SOURCE, Flags::OPEN_SOURCE),
+            (LineFlags::BIAS_PULL_UP, Flags::BIAS_PULL_UP),
+            (LineFlags::BIAS_PULL_DOWN, Flags::BIAS_PULL_DOWN),
+            (LineFlags::BIAS_DISABLED, Flags::BIAS_DISABLE),
+        ];
+
+        let name = unsafe {CStr::from_ptr(info.name.as_ptr())}.to_string_lossy().into_owned();
+        let consumer = unsafe {CStr::from_ptr(info.consumer.as_ptr())}.to_string_lossy().into_owned();
+        let line_flags = LineFlags::from_bits_truncate(info.flags);
+        let flags = FLAGS.iter()
+            .filter(|&&(line_flag, _)| line_flags.contains(line_flag))
+            .fold(Flags::empty(), |flags, &(_, flag)| flags | flag);
+
+        LineInfo {gpio: info.offset, name: Some(name).filter(|s| !s.is_empty()), consumer: Some(consumer).filter(|s| !s.is_empty()), flags}
+    }
+}
+
+/// Kind of change reported by `GpioChip::read_line_info_event()`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineChange {
+    /// The gpio has been requested
+    Requested,
+    /// The gpio has been released
+    Released,
+    /// The configuration of the gpio changed
+    Config,
+}
+
+/// A change of a gpio watched with `GpioChip::watch_line()`
+#[derive(Clone, Debug)]
+pub struct LineInfoChange {
+    /// The gpio information after the change
+    pub info: LineInfo,
+    /// The kind of change
+    pub kind: LineChange,
+    /// timestamp in ns
+    pub timestamp: u64,
+}
+
+impl LineInfoChange {
+    fn from_raw(record: &[u8]) -> io::Result<LineInfoChange> {
+        if record.len() < std::mem::size_of::<ioctl::gpioline_info_changed>() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not enough data received"));
+        }
+        let change: ioctl::gpioline_info_changed = unsafe { std::ptr::read_unaligned(record.as_ptr() as *const _) };
+
+        let kind = match change.event_type {
+            1 => LineChange::Requested,
+            2 => LineChange::Released,
+            3 => LineChange::Config,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown line change type")),
+        };
+
+        Ok(LineInfoChange {info: LineInfo::from_raw(&change.info), kind, timestamp: change.timestamp})
+    }
+}
+
+/// Timestamped log of the changes of watched gpios
+///
+/// This helps to find out, which process keeps reconfiguring a gpio. Watch
+/// the gpios with `GpioChip::watch_lines()` and call `update()` regularly,
+/// or feed changes read elsewhere into `record()`.
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate gpiochip as gpio;
+///
+/// fn main() {
+///     let chip = gpio::GpioChip::new("/dev/gpiochip0").unwrap();
+///     let mut history = gpio::LineHistory::new();
+///
+///     chip.watch_lines(&[0, 1]).unwrap();
+///     while chip.wait_for_line_info_event(10000).unwrap() {
+///         history.update(&chip).unwrap();
+///     }
+///
+///     for (timestamp, direction) in history.direction_changes(0) {
+///         println!("{}: {:?}", timestamp, direction);
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LineHistory {
+    changes: BTreeMap<u32, Vec<LineInfoChange>>,
+}
+
+impl LineHistory {
+    /// Create an empty history
+    pub fn new() -> LineHistory {
+        LineHistory::default()
+    }
+
+    /// Add a change to the history of its gpio
+    pub fn record(&mut self, change: LineInfoChange) {
+        self.changes.entry(change.info.gpio).or_default().push(change);
+    }
+
+    /// Read all queued changes from `chip` without blocking and record them
+    ///
+    /// Returns the number of recorded changes.
+    pub fn update(&mut self, chip: &GpioChip) -> io::Result<usize> {
+        let changes = chip.read_line_info_events()?;
+        let count = changes.len();
+
+        for change in changes {
+            self.record(change);
+        }
+
+        Ok(count)
+    }
+
+    /// Get the gpios with recorded changes
+    pub fn gpios(&self) -> impl Iterator<Item = u32> + '_ {
+        self.changes.keys().copied()
+    }
+
+    /// Get the recorded changes of `gpio` in the order they occurred
+    pub fn history(&self, gpio: u32) -> &[LineInfoChange] {
+        self.changes.get(&gpio).map_or(&[], |changes| changes.as_slice())
+    }
+
+    /// Get the timestamps at which the direction of `gpio` changed
+    ///
+    /// The first recorded change is always included, since the direction
+    /// before it is unknown.
+    pub fn direction_changes(&self, gpio: u32) -> Vec<(u64, Direction)> {
+        let mut result: Vec<(u64, Direction)> = Vec::new();
+
+        for change in self.history(gpio) {
+            let direction = if change.info.flags.is_output() { Direction::Output } else { Direction::Input };
+
+            if result.last().is_none_or(|&(_, last)| last != direction) {
+                result.push((change.timestamp, direction));
+            }
+        }
+
+        result
+    }
+
+    /// Forget all recorded changes
+    pub fn clear(&mut self) {
+        self.changes.clear();
+    }
+}
+
+/// Line configuration returned by `GpioChip::capture_config()`
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineConfig {
+    /// The flags used to request the line
+    pub flags: RequestFlags,
+    /// The value driven by an output line (ignored for inputs)
+    pub default: u8,
+}
+
+/// Data returned by `GpioChip::report()`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChipReport {
+    /// name for the chip
+    pub name: String,
+    /// label for the chip
+    pub label: String,
+    /// amount of gpios provided by the chip
+    pub lines: u32,
+    /// information about every gpio of the chip
+    pub line_info: Vec<LineInfo>,
+}
+
+/// Data returned by `GpioChip::capabilities()`
+///
+/// The kernel does not report, which features the chip's hardware provides.
+/// Missing bias support is silently ignored and open-drain, open-source and
+/// debouncing are emulated, so the flags describe what the running kernel
+/// accepts in requests.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    /// amount of gpios provided by the chip
+    pub lines: u32,
+    /// The v2 uAPI is available (Linux 5.10)
+    pub v2: bool,
+    /// Bias flags are accepted (Linux 5.5)
+    pub bias: bool,
+    /// Debouncing is available, which requires the v2 uAPI
+    pub debounce: bool,
+    /// Open-drain outputs are accepted
+    pub open_drain: bool,
+    /// Open-source outputs are accepted
+    pub open_source: bool,
+    /// Clocks available for event timestamps
+    ///
+    /// `EventClock::Hardware` depends on the hardware and is only listed, if
+    /// a line of the chip currently uses it.
+    pub event_clocks: Vec<EventClock>,
+}
+
+/// Configuration of a single line requested with `GpioChip::request_lines_v2()`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineSettings {
+    /// The line offset
+    pub offset: u32,
+    /// The line configuration
+    pub flags: LineFlags,
+    /// The initial value of an output line (ignored for inputs)
+    pub value: u8,
+}
+
+impl LineSettings {
+    /// Settings for an input line
+    pub fn input(offset: u32) -> LineSettings {
+        LineSettings {offset, flags: LineFlags::INPUT, value: 0}
+    }
+
+    /// Settings for an output line with its initial value
+    pub fn output(offset: u32, value: u8) -> LineSettings {
+        LineSettings {offset, flags: LineFlags::OUTPUT, value}
+    }
+}
+
 #[allow(non_camel_case_types)]
 #[repr(u32)]
-#[derive(PartialEq)]
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EventId {
     /// GPIO changed from low to high
+    #[cfg_attr(feature = "serde", serde(rename = "rising"))]
     RISING_EDGE = 1,
     /// GPIO changed from high to low
+    #[cfg_attr(feature = "serde", serde(rename = "falling"))]
     FALLING_EDGE = 2,
 }
 
+impl std::convert::TryFrom<u32> for EventId {
+    type Error = io::Error;
+
+    /// Convert an event id reported by the kernel, failing with `InvalidData`
+    /// for unknown ids
+    fn try_from(id: u32) -> io::Result<EventId> {
+        match id {
+            1 => Ok(EventId::RISING_EDGE),
+            2 => Ok(EventId::FALLING_EDGE),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown event id {}", id))),
+        }
+    }
+}
+
+/// Direction of a gpio
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    /// The gpio is an input
+    Input,
+    /// The gpio is an output
+    Output,
+}
+
+/// Clock used to timestamp GPIO events
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventClock {
+    /// `CLOCK_MONOTONIC`, the default
+    Monotonic,
+    /// `CLOCK_REALTIME`
+    Realtime,
+    /// Hardware timestamp engine
+    Hardware,
+}
+
+impl From<EventClock> for LineFlags {
+    fn from(clock: EventClock) -> LineFlags {
+        match clock {
+            EventClock::Monotonic => LineFlags::empty(),
+            EventClock::Realtime => LineFlags::EVENT_CLOCK_REALTIME,
+            EventClock::Hardware => LineFlags::EVENT_CLOCK_HTE,
+        }
+    }
+}
+
 /// A GPIO event received from a `GpioEventHandle`
 #[allow(non_camel_case_types)]
 #[repr(C)]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GpioEvent {
     /// timestamp in ns
     pub timestamp: u64,
@@ -159,6 +670,207 @@ pub struct GpioEvent {
     pub id: EventId,
 }
 
+/// Converts event timestamps to `SystemTime` using a cached clock offset
+///
+/// The offset between `CLOCK_MONOTONIC` and `CLOCK_REALTIME` is measured on
+/// creation and by `refresh()`, so converting timestamps does not need any
+/// syscalls. Changes of the realtime clock, e.g. by NTP, are not taken into
+/// account until the offset is refreshed, so long running applications
+/// should refresh it periodically. The measurement itself is accurate to the
+/// time needed for reading both clocks, typically below a microsecond.
+#[derive(Clone, Copy, Debug)]
+pub struct TimestampConverter {
+    offset: i128,
+}
+
+impl TimestampConverter {
+    /// Measure the clock offset and create a converter
+    pub fn new() -> io::Result<TimestampConverter> {
+        let mut converter = TimestampConverter {offset: 0};
+        converter.refresh()?;
+
+        Ok(converter)
+    }
+
+    /// Measure the clock offset again
+    pub fn refresh(&mut self) -> io::Result<()> {
+        let before = monotonic_now()?;
+        let realtime = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let after = monotonic_now()?;
+        let monotonic = before / 2 + after / 2;
+
+        self.offset = realtime as i128 - monotonic as i128;
+        Ok(())
+    }
+
+    /// Convert a `CLOCK_MONOTONIC` timestamp in ns to `SystemTime`
+    pub fn convert(&self, timestamp: u64) -> SystemTime {
+        let realtime = timestamp as i128 + self.offset;
+
+        UNIX_EPOCH + Duration::from_nanos(realtime.max(0) as u64)
+    }
+
+    /// Convert the timestamp of `event` to `SystemTime`
+    pub fn to_system_time(&self, event: &GpioEvent) -> SystemTime {
+        self.convert(event.timestamp)
+    }
+}
+
+impl GpioEvent {
+    /// Get the `CLOCK_MONOTONIC` timestamp as `Duration`
+    pub fn monotonic(&self) -> Duration {
+        Duration::from_nanos(self.timestamp)
+    }
+
+    /// Convert the timestamp to `SystemTime`
+    ///
+    /// This is only valid for events timestamped with `EventClock::Monotonic`.
+    /// The offset between the monotonic and the realtime clock is measured on
+    /// each call, which is not atomic and may be off by a few microseconds.
+    /// Timestamps also shift, if the realtime clock was changed since the
+    /// event occurred. Use a `TimestampConverter` to convert many events with
+    /// a single measured offset.
+    ///
+    /// # Panics
+    ///
+    /// Like `Instant::now()`, this panics if `CLOCK_MONOTONIC` can not be
+    /// read, which does not happen on Linux. Use `TimestampConverter::new()`
+    /// to handle that error instead.
+    pub fn to_system_time(&self) -> SystemTime {
+        TimestampConverter::new().expect("CLOCK_MONOTONIC not available").to_system_time(self)
+    }
+}
+
+/// Typed error for failures, that callers may want to handle specifically
+///
+/// The methods of this crate still return `io::Error`. Errors detected by
+/// the crate itself carry a `GpioError`, which is recovered by converting
+/// the `io::Error` with `GpioError::from()`. All other errors end up as
+/// `GpioError::Io`, except for errors of kind `Unsupported`.
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate gpiochip as gpio;
+///
+/// fn main() {
+///     let chip = gpio::GpioChip::new("/dev/gpiochip0").unwrap();
+///
+///     match chip.request("gpioA", gpio::RequestFlags::INPUT, 200, 0).map_err(gpio::GpioError::from) {
+///         Ok(handle) => println!("value: {}", handle.get().unwrap()),
+///         Err(gpio::GpioError::OffsetOutOfRange { offset, lines }) => println!("{} >= {}", offset, lines),
+///         Err(err) => println!("error: {}", err),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub enum GpioError {
+    /// Any other error, usually returned by the kernel
+    Io(io::Error),
+    /// The gpio offset is not provided by the chip
+    OffsetOutOfRange {
+        /// The requested offset
+        offset: u32,
+        /// The number of lines of the chip
+        lines: u32,
+    },
+    /// The consumer label does not fit into the kernel's label
+    LabelTooLong {
+        /// The length of the label in bytes
+        len: usize,
+    },
+    /// The request flags are invalid, e.g. contain flags excluding each other
+    ConflictingFlags {
+        /// The flags causing the error
+        flags: RequestFlags,
+        /// Why the flags are rejected
+        reason: &'static str,
+    },
+    /// The consumer label contains non-ASCII characters or NUL bytes
+    InvalidLabel,
+    /// A gpio value other than 0 or 1
+    InvalidValue {
+        /// The rejected value
+        value: u8,
+    },
+    /// The number of values does not match the number of gpios
+    ValueCountMismatch {
+        /// The number of gpios
+        expected: usize,
+        /// The number of values provided
+        got: usize,
+    },
+    /// An index or bit beyond the requested gpios
+    IndexOutOfRange {
+        /// The rejected index
+        index: usize,
+        /// The number of requested gpios
+        len: usize,
+    },
+    /// A builder was used without selecting a gpio
+    NoGpio,
+    /// The operation is not supported by the running kernel or handle
+    Unsupported,
+    /// More lines than supported by a single request
+    TooManyLines,
+}
+
+/// Result with a `GpioError`
+pub type GpioResult<T> = Result<T, GpioError>;
+
+impl std::fmt::Display for GpioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GpioError::Io(err) => write!(f, "{}", err),
+            GpioError::OffsetOutOfRange { offset, lines } => write!(f, "gpio offset {} out of range ({} lines)", offset, lines),
+            GpioError::LabelTooLong { len } => write!(f, "consumer label too long ({} bytes, max. 31)", len),
+            GpioError::ConflictingFlags { flags, reason } => write!(f, "invalid request flags {:?}: {}", flags, reason),
+            GpioError::InvalidLabel => write!(f, "consumer label must be ASCII without NUL bytes"),
+            GpioError::InvalidValue { value } => write!(f, "value {} must be 0 or 1", value),
+            GpioError::ValueCountMismatch { expected, got } => write!(f, "{} values given for {} gpios", got, expected),
+            GpioError::IndexOutOfRange { index, len } => write!(f, "index {} out of range ({} gpios)", index, len),
+            GpioError::NoGpio => write!(f, "no gpio specified"),
+            GpioError::Unsupported => write!(f, "operation not supported"),
+            GpioError::TooManyLines => write!(f, "too many lines"),
+        }
+    }
+}
+
+impl std::error::Error for GpioError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GpioError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GpioError {
+    fn from(err: io::Error) -> GpioError {
+        if err.get_ref().is_some_and(|inner| inner.is::<GpioError>()) {
+            return *err.into_inner().unwrap().downcast::<GpioError>().unwrap();
+        }
+
+        if err.kind() == io::ErrorKind::Unsupported {
+            return GpioError::Unsupported;
+        }
+
+        GpioError::Io(err)
+    }
+}
+
+impl From<GpioError> for io::Error {
+    fn from(err: GpioError) -> io::Error {
+        let kind = match err {
+            GpioError::Io(err) => return err,
+            GpioError::Unsupported => io::ErrorKind::Unsupported,
+            _ => io::ErrorKind::InvalidInput,
+        };
+
+        io::Error::new(kind, err)
+    }
+}
+
 /* internal low-level API */
 mod ioctl {
     use std::os::raw::c_char;
@@ -181,6 +893,15 @@ mod ioctl {
         pub consumer: [c_char; 32],
     }
 
+    #[allow(non_camel_case_types)]
+    #[repr(C)]
+    pub struct gpioline_info_changed {
+        pub info: gpioline_info,
+        pub timestamp: u64,
+        pub event_type: u32,
+        pub padding: [u32; 5],
+    }
+
     #[allow(non_camel_case_types)]
     #[repr(C)]
     pub struct gpiohandle_request {
@@ -202,12 +923,27 @@ mod ioctl {
         pub fd: RawFd,
     }
 
+    #[allow(non_camel_case_types)]
+    #[repr(C)]
+    pub struct gpioevent_data {
+        pub timestamp: u64,
+        pub id: u32,
+    }
+
     #[allow(non_camel_case_types)]
     #[repr(C)]
     pub struct gpiohandle_data {
         pub values: [u8; 64],
     }
 
+    #[allow(non_camel_case_types)]
+    #[repr(C)]
+    pub struct gpiohandle_config {
+        pub flags: u32,
+        pub default_values: [u8; 64],
+        pub padding: [u32; 4],
+    }
+
     const GPIO_IOC_MAGIC: u8 = 0xB4;
 
     ioctl_read!(get_chipinfo, GPIO_IOC_MAGIC, 0x01, gpiochip_info );
@@ -216,6 +952,107 @@ mod ioctl {
     ioctl_readwrite!(get_lineevent, GPIO_IOC_MAGIC, 0x04, gpioevent_request );
     ioctl_readwrite!(get_line_values, GPIO_IOC_MAGIC, 0x08, gpiohandle_data );
     ioctl_readwrite!(set_line_values, GPIO_IOC_MAGIC, 0x09, gpiohandle_data );
+    ioctl_readwrite!(set_config, GPIO_IOC_MAGIC, 0x0A, gpiohandle_config );
+    ioctl_readwrite!(watch_lineinfo, GPIO_IOC_MAGIC, 0x0B, gpioline_info );
+    ioctl_readwrite!(unwatch_lineinfo, GPIO_IOC_MAGIC, 0x0C, u32 );
+
+    /* v2 uAPI, available since Linux 5.10 */
+    pub mod v2 {
+        use std::os::raw::c_char;
+        use std::os::unix::io::RawFd;
+
+        pub const GPIO_V2_LINES_MAX: usize = 64;
+        pub const GPIO_V2_LINE_NUM_ATTRS_MAX: usize = 10;
+
+        pub const GPIO_V2_LINE_ATTR_ID_FLAGS: u32 = 1;
+        pub const GPIO_V2_LINE_ATTR_ID_OUTPUT_VALUES: u32 = 2;
+        pub const GPIO_V2_LINE_ATTR_ID_DEBOUNCE: u32 = 3;
+
+        #[allow(non_camel_case_types)]
+        #[repr(C)]
+        pub struct gpio_v2_line_values {
+            pub bits: u64,
+            pub mask: u64,
+        }
+
+        #[allow(non_camel_case_types)]
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        pub union gpio_v2_line_attribute_value {
+            pub flags: u64,
+            pub values: u64,
+            pub debounce_period_us: u32,
+        }
+
+        #[allow(non_camel_case_types)]
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        pub struct gpio_v2_line_attribute {
+            pub id: u32,
+            pub padding: u32,
+            pub value: gpio_v2_line_attribute_value,
+        }
+
+        #[allow(non_camel_case_types)]
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        pub struct gpio_v2_line_config_attribute {
+            pub attr: gpio_v2_line_attribute,
+            pub mask: u64,
+        }
+
+        #[allow(non_camel_case_types)]
+        #[repr(C)]
+        pub struct gpio_v2_line_config {
+            pub flags: u64,
+            pub num_attrs: u32,
+            pub padding: [u32; 5],
+            pub attrs: [gpio_v2_line_config_attribute; GPIO_V2_LINE_NUM_ATTRS_MAX],
+        }
+
+        #[allow(non_camel_case_types)]
+        #[repr(C)]
+        pub struct gpio_v2_line_request {
+            pub offsets: [u32; GPIO_V2_LINES_MAX],
+            pub consumer: [c_char; 32],
+            pub config: gpio_v2_line_config,
+            pub num_lines: u32,
+            pub event_buffer_size: u32,
+            pub padding: [u32; 5],
+            pub fd: RawFd,
+        }
+
+        #[allow(non_camel_case_types)]
+        #[repr(C)]
+        pub struct gpio_v2_line_event {
+            pub timestamp_ns: u64,
+            pub id: u32,
+            pub offset: u32,
+            pub seqno: u32,
+            pub line_seqno: u32,
+            pub padding: [u32; 6],
+        }
+
+        #[allow(non_camel_case_types)]
+        #[repr(C)]
+        pub struct gpio_v2_line_info {
+            pub name: [c_char; 32],
+            pub consumer: [c_char; 32],
+            pub offset: u32,
+            pub num_attrs: u32,
+            pub flags: u64,
+            pub attrs: [gpio_v2_line_attribute; GPIO_V2_LINE_NUM_ATTRS_MAX],
+            pub padding: [u32; 4],
+        }
+
+        const GPIO_IOC_MAGIC: u8 = 0xB4;
+
+        ioctl_readwrite!(get_line_info, GPIO_IOC_MAGIC, 0x05, gpio_v2_line_info );
+        ioctl_readwrite!(get_line, GPIO_IOC_MAGIC, 0x07, gpio_v2_line_request );
+        ioctl_readwrite!(set_line_config, GPIO_IOC_MAGIC, 0x0D, gpio_v2_line_config );
+        ioctl_readwrite!(get_line_values, GPIO_IOC_MAGIC, 0x0E, gpio_v2_line_values );
+        ioctl_readwrite!(set_line_values, GPIO_IOC_MAGIC, 0x0F, gpio_v2_line_values );
+    }
 }
 
 fn from_nix_error(err: ::nix::Error) -> io::Error {
@@ -232,16 +1069,411 @@ fn from_nix_result<T>(res: ::nix::Result<T>) -> io::Result<T> {
     }
 }
 
-/// Provide high-level access to Linux gpiochip Driver
-pub struct GpioChip {
-    file: std::fs::File,
-
-    /// name for the chip
+/// Like `from_nix_result()`, but reporting kernels without v2 uAPI as `Unsupported`
+fn from_v2_result<T>(res: ::nix::Result<T>) -> io::Result<T> {
+    match res {
+        Err(nix::Error::Sys(nix::errno::Errno::ENOTTY)) => Err(io::Error::new(io::ErrorKind::Unsupported, "GPIO v2 uAPI not supported by the kernel")),
+        res => from_nix_result(res),
+    }
+}
+
+/// Check if a v2 request failed, because the kernel does not support `clock`
+///
+/// Kernels before 5.11 reject the realtime flag and kernels before 5.19 the
+/// HTE flag as unknown with `EINVAL`. Newer kernels without HTE support
+/// reject the HTE flag with `EOPNOTSUPP`. `EINVAL` on newer kernels is caused
+/// by something else and is not remapped.
+fn clock_rejected(clock: EventClock, err: &io::Error) -> bool {
+    let since = match clock {
+        EventClock::Monotonic => return false,
+        EventClock::Realtime => (5, 11),
+        EventClock::Hardware => (5, 19),
+    };
+
+    match err.raw_os_error() {
+        Some(libc::EINVAL) => kernel_version().is_some_and(|version| version < since),
+        Some(libc::EOPNOTSUPP) => clock == EventClock::Hardware,
+        _ => false,
+    }
+}
+
+/// Build the v2 line config of an event handle
+fn event_line_config(handleflags: RequestFlags, eventflags: EventRequestFlags, debounce: Duration, clock: EventClock) -> io::Result<ioctl::v2::gpio_v2_line_config> {
+    let mut config: ioctl::v2::gpio_v2_line_config = unsafe { std::mem::zeroed() };
+
+    config.flags = (LineFlags::INPUT | LineFlags::from(handleflags) | LineFlags::from(eventflags) | LineFlags::from(clock)).bits;
+
+    if debounce != Duration::from_secs(0) {
+        let period = debounce.as_micros();
+        if period > u32::MAX as u128 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "debounce period too long"));
+        }
+
+        let attr = &mut config.attrs[0];
+        attr.attr.id = ioctl::v2::GPIO_V2_LINE_ATTR_ID_DEBOUNCE;
+        attr.attr.value.debounce_period_us = period as u32;
+        attr.mask = 1;
+        config.num_attrs = 1;
+    }
+
+    Ok(config)
+}
+
+/// Read `CLOCK_MONOTONIC`, the clock used for event timestamps, in ns
+fn monotonic_now() -> io::Result<u64> {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+
+    if unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64)
+}
+
+/// Read the system clock matching `clock` in ns
+///
+/// Returns `None` for `EventClock::Hardware`, which has no system clock.
+fn clock_now(clock: EventClock) -> io::Result<Option<u64>> {
+    Ok(match clock {
+        EventClock::Monotonic => Some(monotonic_now()?),
+        EventClock::Realtime => Some(SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)),
+        EventClock::Hardware => None,
+    })
+}
+
+/// Enable or disable `O_NONBLOCK` on `fd`
+fn set_fd_nonblocking(fd: RawFd, nonblocking: bool) -> io::Result<()> {
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let flags = if nonblocking { flags | libc::O_NONBLOCK } else { flags & !libc::O_NONBLOCK };
+    if unsafe { libc::fcntl(fd, libc::F_SETFL, flags) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Close `file`, returning the error of close(), that is ignored by drop
+fn close_file(file: std::fs::File) -> io::Result<()> {
+    if unsafe { libc::close(file.into_raw_fd()) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Get major and minor version of the running kernel
+fn kernel_version() -> Option<(u32, u32)> {
+    let mut uts: libc::utsname = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::uname(&mut uts) } < 0 {
+        return None;
+    }
+
+    let release = unsafe { CStr::from_ptr(uts.release.as_ptr()) }.to_string_lossy().into_owned();
+    let mut parts = release.split(|c: char| !c.is_ascii_digit());
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+
+    Some((major, minor))
+}
+
+/// Compute the request flags of `request_by_name_smart()`
+fn smart_flags(base_flags: RequestFlags, name: &str, active_low: Option<bool>) -> RequestFlags {
+    let mut flags = base_flags;
+
+    flags.set(RequestFlags::ACTIVE_LOW, active_low.unwrap_or_else(|| base_flags.contains(RequestFlags::ACTIVE_LOW) || infers_active_low(name)));
+    flags
+}
+
+/// Compute the request flags of switching `flags` to output or input mode
+fn direction_flags(flags: RequestFlags, output: bool) -> RequestFlags {
+    let flags = flags - (RequestFlags::INPUT | RequestFlags::OUTPUT);
+
+    if output {
+        flags | RequestFlags::OUTPUT
+    } else {
+        (flags - (RequestFlags::OPEN_DRAIN | RequestFlags::OPEN_SOURCE)) | RequestFlags::INPUT
+    }
+}
+
+/// Open `path` read-only and close-on-exec
+fn open_cloexec(path: &std::path::Path) -> io::Result<std::fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    std::fs::OpenOptions::new().read(true).custom_flags(libc::O_CLOEXEC).open(path)
+}
+
+/// Decide the value of `samples` reads, of which `high` returned 1
+///
+/// The value read more often wins, if it was read at least `agree` times.
+fn majority_vote(samples: usize, high: usize, agree: usize) -> io::Result<u8> {
+    let low = samples - high;
+
+    let (value, count) = match high.cmp(&low) {
+        std::cmp::Ordering::Greater => (1, high),
+        std::cmp::Ordering::Less => (0, low),
+        std::cmp::Ordering::Equal => return Err(io::Error::new(io::ErrorKind::InvalidData, "samples did not agree")),
+    };
+
+    if count < agree {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "samples did not agree"));
+    }
+
+    Ok(value)
+}
+
+/// Check if `fd` refers to an open file descriptor
+fn fd_is_valid(fd: RawFd) -> bool {
+    unsafe { libc::fcntl(fd, libc::F_GETFD) != -1 }
+}
+
+/// List the `/dev/gpiochip*` devices sorted by chip number
+fn chip_paths() -> io::Result<Vec<std::path::PathBuf>> {
+    let mut chips = Vec::new();
+
+    for entry in std::fs::read_dir("/dev")? {
+        let entry = entry?;
+        let number = match entry.file_name().to_str() {
+            Some(name) if name.starts_with("gpiochip") => name[8..].parse::<u32>().ok(),
+            _ => None,
+        };
+
+        if let Some(number) = number {
+            chips.push((number, entry.path()));
+        }
+    }
+
+    chips.sort();
+    Ok(chips.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Pairs of request flags, that can not be combined
+const CONFLICTING_FLAGS: &[(RequestFlags, RequestFlags)] = &[
+    (RequestFlags::INPUT, RequestFlags::OUTPUT),
+    (RequestFlags::OPEN_DRAIN, RequestFlags::OPEN_SOURCE),
+    (RequestFlags::BIAS_PULL_UP, RequestFlags::BIAS_PULL_DOWN),
+    (RequestFlags::BIAS_PULL_UP, RequestFlags::BIAS_DISABLE),
+    (RequestFlags::BIAS_PULL_DOWN, RequestFlags::BIAS_DISABLE),
+];
+
+/// Add the name of the requested gpio to the message of `err`
+///
+/// Errors carrying a `GpioError` are returned unchanged, since rewrapping
+/// them would lose the typed error.
+fn name_context(err: io::Error, name: &str) -> io::Error {
+    if err.get_ref().is_some_and(|inner| inner.is::<GpioError>()) {
+        return err;
+    }
+
+    io::Error::new(err.kind(), format!("failed to request gpio {:?}: {}", name, err))
+}
+
+/// Check `flags` for combinations, that are rejected by the kernel
+fn check_flags(flags: RequestFlags) -> GpioResult<()> {
+    for &(a, b) in CONFLICTING_FLAGS {
+        if flags.contains(a | b) {
+            return Err(GpioError::ConflictingFlags { flags: a | b, reason: "flags exclude each other" });
+        }
+    }
+
+    let drive = RequestFlags::OPEN_DRAIN | RequestFlags::OPEN_SOURCE;
+    if flags.intersects(drive) && !flags.contains(RequestFlags::OUTPUT) {
+        return Err(GpioError::ConflictingFlags { flags: flags & drive, reason: "open-drain and open-source require output mode" });
+    }
+
+    let bias = RequestFlags::BIAS_PULL_UP | RequestFlags::BIAS_PULL_DOWN | RequestFlags::BIAS_DISABLE;
+    if flags.intersects(bias) && !flags.intersects(RequestFlags::INPUT | RequestFlags::OUTPUT) {
+        return Err(GpioError::ConflictingFlags { flags: flags & bias, reason: "bias flags require input or output mode" });
+    }
+
+    Ok(())
+}
+
+/// Copy `label` into a kernel label buffer, including its NUL terminator
+///
+/// Only ASCII without NUL bytes is accepted, since the kernel treats the
+/// label as a C string and multibyte characters could be cut in half.
+fn copy_label(dst: &mut [std::os::raw::c_char; 32], label: &str) -> GpioResult<()> {
+    if label.len() >= dst.len() {
+        return Err(GpioError::LabelTooLong { len: label.len() });
+    }
+
+    if !label.is_ascii() || label.contains('\0') {
+        return Err(GpioError::InvalidLabel);
+    }
+
+    for (d, &b) in dst.iter_mut().zip(label.as_bytes()) {
+        *d = b as std::os::raw::c_char;
+    }
+    dst[label.len()] = 0;
+
+    Ok(())
+}
+
+/// Check, that a gpio value is either 0 or 1
+fn check_value(value: u8) -> GpioResult<()> {
+    if value > 1 {
+        return Err(GpioError::InvalidValue { value });
+    }
+
+    Ok(())
+}
+
+/// Check the handle flags of an event request, which is always an input
+fn check_event_flags(handleflags: RequestFlags) -> GpioResult<()> {
+    let output = RequestFlags::OUTPUT | RequestFlags::OPEN_DRAIN | RequestFlags::OPEN_SOURCE;
+    if handleflags.intersects(output) {
+        return Err(GpioError::ConflictingFlags { flags: handleflags & output, reason: "events can only be requested for inputs" });
+    }
+
+    check_flags(handleflags | RequestFlags::INPUT)
+}
+
+/// Suffixes marking active-low gpio names
+const ACTIVE_LOW_SUFFIXES: &[&str] = &["_n", "_N", "#"];
+
+/// Check if a gpio name marks it as active-low
+///
+/// Recognized are the suffixes `_n`, `_N` and `#`, e.g. `"reset_n"` or
+/// `"RESET#"`.
+pub fn infers_active_low(name: &str) -> bool {
+    ACTIVE_LOW_SUFFIXES.iter().any(|suffix| name.len() > suffix.len() && name.ends_with(suffix))
+}
+
+/// Compute the deadline `timeout` from now, saturating at 100 years
+///
+/// This keeps `Duration::MAX` usable as "wait forever", which would overflow
+/// `Instant`. Since poll() timeouts are limited to about 24 days, waiting
+/// loops have to check the deadline again, when poll() times out.
+fn deadline_after(timeout: Duration) -> Instant {
+    const MAX_TIMEOUT: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+
+    Instant::now() + timeout.min(MAX_TIMEOUT)
+}
+
+/// Convert the time left until `deadline` into a poll() timeout, rounding up
+fn poll_timeout(deadline: Instant) -> i32 {
+    duration_to_poll_timeout(Some(deadline.saturating_duration_since(Instant::now())))
+}
+
+/// Convert `timeout` into a poll() timeout, rounding up and saturating
+///
+/// `None` is converted into -1, which blocks forever.
+fn duration_to_poll_timeout(timeout: Option<Duration>) -> i32 {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return -1,
+    };
+    let ms = timeout.checked_add(Duration::from_nanos(999_999)).unwrap_or(Duration::MAX).as_millis();
+
+    ms.min(i32::MAX as u128) as i32
+}
+
+/// Match `name` against a glob `pattern` supporting `*` and `?`
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Lifecycle step of gpios reported to an audit hook
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AuditAction {
+    /// The gpios have been requested
+    Acquired,
+    /// The handle has been dropped, releasing the gpios
+    Released,
+    /// The fd of the handle has been handed off, e.g. with `into_raw_fd()`
+    ///
+    /// The gpios stay requested and no `Released` record follows, since
+    /// closing the fd can not be observed anymore.
+    Detached,
+}
+
+/// Record passed to the hook registered with `GpioChip::set_audit_hook()`
+#[derive(Clone, Debug)]
+pub struct AuditRecord {
+    /// What happened to the gpios
+    pub action: AuditAction,
+    /// The consumer label of the handle
+    pub consumer: String,
+    /// The gpios of the handle
+    pub gpios: Vec<u32>,
+    /// Time at which the action happened
+    pub timestamp: SystemTime,
+}
+
+type AuditHook = Arc<dyn Fn(&AuditRecord) + Send + Sync>;
+
+/// Reports the release of a handle's gpios to the audit hook when dropped
+struct AuditToken {
+    hook: AuditHook,
+    consumer: String,
+    gpios: Vec<u32>,
+    detached: AtomicBool,
+}
+
+impl AuditToken {
+    fn report(&self, action: AuditAction) {
+        (self.hook)(&AuditRecord {action, consumer: self.consumer.clone(), gpios: self.gpios.clone(), timestamp: SystemTime::now()});
+    }
+}
+
+impl Drop for AuditToken {
+    fn drop(&mut self) {
+        if !self.detached.load(Ordering::Relaxed) {
+            self.report(AuditAction::Released);
+        }
+    }
+}
+
+/// Report a handle's fd as handed off, so its drop does not report a release
+///
+/// The token is shared by all clones of the handle, so dropping the other
+/// clones does not report a release either.
+fn detach_audit(audit: &Option<Arc<AuditToken>>) {
+    if let Some(token) = audit {
+        if !token.detached.swap(true, Ordering::Relaxed) {
+            token.report(AuditAction::Detached);
+        }
+    }
+}
+
+/// Provide high-level access to Linux gpiochip Driver
+pub struct GpioChip {
+    file: std::fs::File,
+
+    /// name for the chip
     pub name: String,
     /// label for the chip
     pub label: String,
     /// amount of gpios provided by the chip
     pub lines: u32,
+
+    audit: Option<AuditHook>,
 }
 
 /// A GPIO handle acquired from the gpiochip
@@ -250,6 +1482,8 @@ pub struct GpioHandle {
     pub gpio: u32,
     pub consumer: String,
     pub flags: RequestFlags,
+    _audit: Option<Arc<AuditToken>>,
+    v2: bool,
 }
 
 /// A GPIO array handle acquired from the gpiochip
@@ -258,6 +1492,16 @@ pub struct GpioArrayHandle {
     pub gpios: Box<[u32]>,
     pub consumer: String,
     pub flags: RequestFlags,
+    names: Box<[Option<String>]>,
+    _audit: Option<Arc<AuditToken>>,
+}
+
+/// Multiple lines acquired from the gpiochip using the v2 API
+pub struct GpioLinesV2 {
+    file: std::fs::File,
+    pub offsets: Box<[u32]>,
+    pub consumer: String,
+    _audit: Option<Arc<AuditToken>>,
 }
 
 /// A GPIO event handle acquired from the gpiochip
@@ -266,261 +1510,2906 @@ pub struct GpioEventHandle {
     pub gpio: u32,
     pub eventflags: EventRequestFlags,
     pub handleflags: RequestFlags,
+    last_timestamp: AtomicU64,
+    _audit: Option<Arc<AuditToken>>,
+    v2: bool,
+    clock: EventClock,
+    debounce: Duration,
 }
 
 impl GpioEventHandle {
-    /// Read GpioEvent
-    pub fn read(&self) -> io::Result<GpioEvent> {
-        let mut buf = [0 as u8; std::mem::size_of::<GpioEvent>()];
-        let size = try!(from_nix_result(nix::unistd::read(self.file.as_raw_fd(), &mut buf)));
-        if size < std::mem::size_of::<GpioEvent>() {
+    /// Size of a single event record read from the fd
+    fn record_size(&self) -> usize {
+        if self.v2 {
+            std::mem::size_of::<ioctl::v2::gpio_v2_line_event>()
+        } else {
+            std::mem::size_of::<ioctl::gpioevent_data>()
+        }
+    }
+
+    /// Convert an event record read from the fd into a GpioEvent
+    fn parse_event(&self, record: &[u8]) -> io::Result<GpioEvent> {
+        if record.len() < self.record_size() {
             return Err(io::Error::new(io::ErrorKind::InvalidData, "not enough data received"));
         }
-        let s: GpioEvent = unsafe { std::ptr::read(buf.as_ptr() as *const _) };
+
+        let s: GpioEvent = if self.v2 {
+            let event: ioctl::v2::gpio_v2_line_event = unsafe { std::ptr::read_unaligned(record.as_ptr() as *const _) };
+
+            GpioEvent {timestamp: event.timestamp_ns, id: EventId::try_from(event.id)?}
+        } else {
+            let event: ioctl::gpioevent_data = unsafe { std::ptr::read_unaligned(record.as_ptr() as *const _) };
+
+            GpioEvent {timestamp: event.timestamp, id: EventId::try_from(event.id)?}
+        };
+        self.last_timestamp.store(s.timestamp, Ordering::Relaxed);
 
         Ok(s)
     }
 
-    /// Flush event buffer
-    pub fn flush(&self) -> io::Result<()> {
-        let mut bitmap = try!(wait_for_event(&[&self], 0));
+    /// Read GpioEvent
+    pub fn read(&self) -> io::Result<GpioEvent> {
+        let mut buf = [0u8; std::mem::size_of::<ioctl::v2::gpio_v2_line_event>()];
+        let record = &mut buf[..self.record_size()];
+
+        let size = io::Read::read(&mut &*self, record)?;
+        self.parse_event(&record[..size])
+    }
+
+    /// Read a GpioEvent without blocking
+    ///
+    /// Returns `None` if no event is queued. This works for handles in both
+    /// blocking and non-blocking mode.
+    pub fn try_read(&self) -> io::Result<Option<GpioEvent>> {
+        if wait_for_event(&[self], 0)? == 0 {
+            return Ok(None);
+        }
+
+        match self.read() {
+            Ok(event) => Ok(Some(event)),
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Switch the fd between blocking and non-blocking mode
+    ///
+    /// In non-blocking mode `read()` fails with `WouldBlock` instead of
+    /// waiting for an event.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        set_fd_nonblocking(self.file.as_raw_fd(), nonblocking)
+    }
+
+    /// Read up to `max` queued GpioEvents using a single read
+    ///
+    /// If the fd is in non-blocking mode and no event is queued, an empty
+    /// list is returned. `max` is limited to 256 events, 16 times the size of
+    /// the kernel's event FIFO, so `usize::MAX` reads all queued events.
+    pub fn read_events(&self, max: usize) -> io::Result<Vec<GpioEvent>> {
+        const MAX_EVENTS: usize = 16 * 16;
+
+        if max == 0 {
+            return Ok(Vec::new());
+        }
+
+        let record = self.record_size();
+        let mut buf = vec![0u8; max.min(MAX_EVENTS) * record];
+
+        let size = match from_nix_result(nix::unistd::read(self.file.as_raw_fd(), &mut buf)) {
+            Ok(size) => size,
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        buf[..size].chunks(record).map(|r| self.parse_event(r)).collect()
+    }
+
+    /// Read all queued GpioEvents without blocking
+    pub fn read_all(&self) -> io::Result<Vec<GpioEvent>> {
+        let mut events = Vec::new();
+
+        while wait_for_event(&[self], 0)? != 0 {
+            events.push(self.read()?);
+        }
+
+        Ok(events)
+    }
+
+    /// Flush event buffer
+    pub fn flush(&self) -> io::Result<()> {
+        let mut bitmap = wait_for_event(&[self], 0)?;
+
+        while bitmap != 0 {
+            self.read()?;
+            bitmap = wait_for_event(&[self], 0)?;
+        }
+
+        Ok(())
+    }
+
+    /// Flush event buffer and read the current GPIO value
+    ///
+    /// Returns the number of discarded events and the value read afterwards.
+    pub fn drain_to_current(&self) -> io::Result<(usize, u8)> {
+        let mut count = 0;
+
+        while wait_for_event(&[self], 0)? != 0 {
+            self.read()?;
+            count += 1;
+        }
+
+        Ok((count, self.get()?))
+    }
+
+    /// Iterate over the events, blocking until the next one arrives
+    ///
+    /// The iterator never ends on its own. If reading fails, the error is
+    /// yielded and the iteration stops.
+    pub fn events(&self) -> impl Iterator<Item = io::Result<GpioEvent>> + '_ {
+        let mut failed = false;
+
+        std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+
+            let event = self.read();
+            failed = event.is_err();
+            Some(event)
+        })
+    }
+
+    /// Wait for the next GpioEvent and read it
+    ///
+    /// Returns `None` if no event arrives within `timeout`. `None` as timeout
+    /// waits forever.
+    pub fn wait_for_edge(&self, timeout: Option<Duration>) -> io::Result<Option<GpioEvent>> {
+        if wait_for_event_timeout(&[self], timeout)? == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(self.read()?))
+    }
+
+    /// Read GpioEvent, failing with `TimedOut` if none arrives before `deadline`
+    fn read_before(&self, deadline: Instant) -> io::Result<GpioEvent> {
+        while wait_for_event(&[self], poll_timeout(deadline))? == 0 {
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "no event received before timeout"));
+            }
+        }
+
+        self.read()
+    }
+
+    /// Measure the width of a pulse
+    ///
+    /// Waits for an edge of type `level` followed by the opposite edge and
+    /// returns the time between both, as reported by the kernel timestamps.
+    /// The handle must have been requested with `EventRequestFlags::BOTH_EDGES`,
+    /// otherwise `InvalidInput` is returned. If the whole pulse is not seen
+    /// within `timeout`, `TimedOut` is returned.
+    pub fn measure_pulse(&self, level: EventId, timeout: Duration) -> io::Result<Duration> {
+        if !self.is_both_edges() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "measuring pulses requires both edges"));
+        }
+
+        let deadline = deadline_after(timeout);
+        let mut start = None;
+
+        loop {
+            let event = self.read_before(deadline)?;
+
+            if event.id == level {
+                start = Some(event.timestamp);
+            } else if let Some(timestamp) = start {
+                return Ok(Duration::from_nanos(event.timestamp.saturating_sub(timestamp)));
+            }
+        }
+    }
+
+    /// Get GPIO value
+    pub fn get(&self) -> io::Result<u8> {
+        if self.v2 {
+            let mut values = ioctl::v2::gpio_v2_line_values { bits: 0, mask: 1 };
+
+            from_nix_result(unsafe {
+                ioctl::v2::get_line_values(self.file.as_raw_fd(), &mut values)
+            })?;
+
+            return Ok((values.bits & 1) as u8);
+        }
+
+        let mut data = ioctl::gpiohandle_data { values: [0; 64] };
+
+        from_nix_result(unsafe {
+            ioctl::get_line_values(self.file.as_raw_fd(), &mut data)
+        })?;
+
+        Ok(data.values[0])
+    }
+
+    /// Get GPIO value as bool
+    pub fn get_bool(&self) -> io::Result<bool> {
+        Ok(self.get()? != 0)
+    }
+
+    /// Measure the frequency of the signal in Hz
+    ///
+    /// Edges are collected for the `gate` time and the frequency is computed
+    /// from the kernel timestamps of the first and last edge, instead of the
+    /// gate time. If both edges are requested, only rising edges are counted,
+    /// so that the duty cycle does not affect the result. If fewer than two
+    /// edges are seen, 0 is returned.
+    pub fn measure_frequency(&self, gate: Duration) -> io::Result<f64> {
+        let deadline = deadline_after(gate);
+        let both_edges = self.is_both_edges();
+        let mut first = None;
+        let mut last = 0;
+        let mut count: u64 = 0;
+
+        while Instant::now() < deadline {
+            if wait_for_event(&[self], poll_timeout(deadline))? == 0 {
+                continue;
+            }
+
+            let event = self.read()?;
+            if both_edges && event.id != EventId::RISING_EDGE {
+                continue;
+            }
+
+            if first.is_none() {
+                first = Some(event.timestamp);
+            }
+            last = event.timestamp;
+            count += 1;
+        }
+
+        match first {
+            Some(first) if count > 1 && last > first => Ok((count - 1) as f64 * 1e9 / (last - first) as f64),
+            _ => Ok(0.0),
+        }
+    }
+
+    /// Check if the current GPIO value is consistent with an event
+    ///
+    /// Returns false, if the value does not match the edge of `event` (low
+    /// after a rising edge or high after a falling edge), which means another
+    /// edge happened after it. The value is read when this is called, so an
+    /// edge occuring meanwhile is indistinguishable from a glitch and a fast
+    /// double edge can still go unnoticed.
+    pub fn verify_event(&self, event: &GpioEvent) -> io::Result<bool> {
+        let value = self.get()?;
+
+        Ok(match event.id {
+            EventId::RISING_EDGE => value != 0,
+            EventId::FALLING_EDGE => value == 0,
+        })
+    }
+
+    /// Get the time passed since the last event read from this handle
+    ///
+    /// Returns `None` if no event has been read yet or if the events are
+    /// timestamped by the hardware timestamp engine.
+    pub fn time_since_last_event(&self) -> Option<Duration> {
+        let last = self.last_timestamp.load(Ordering::Relaxed);
+
+        if last == 0 {
+            return None;
+        }
+
+        let now = clock_now(self.clock).ok()??;
+
+        Some(Duration::from_nanos(now.saturating_sub(last)))
+    }
+
+    /// Change the edges generating events
+    ///
+    /// The line stays requested, so no edge is lost as with releasing and
+    /// requesting it again. Events already queued are kept. This requires the
+    /// v2 API. Handles requested with the v1 API can not be reconfigured and
+    /// return an `Unsupported` error.
+    pub fn set_edges(&mut self, eventflags: EventRequestFlags) -> io::Result<()> {
+        if !self.v2 {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "v1 event handles can not be reconfigured"));
+        }
+
+        let mut config = event_line_config(self.handleflags, eventflags, self.debounce, self.clock)?;
+
+        from_v2_result(unsafe {
+            ioctl::v2::set_line_config(self.file.as_raw_fd(), &mut config)
+        })?;
+
+        self.eventflags = eventflags;
+        Ok(())
+    }
+
+    /// Get the clock used to timestamp the events of this handle
+    pub fn clock(&self) -> EventClock {
+        self.clock
+    }
+
+    /// Get the edges, that have been requested for this handle
+    pub fn eventflags(&self) -> EventRequestFlags {
+        self.eventflags
+    }
+
+    /// Check if both edges generate events
+    ///
+    /// If only a single edge has been requested, all events read from the
+    /// handle have the same `EventId` and checking it can be skipped.
+    pub fn is_both_edges(&self) -> bool {
+        self.eventflags.contains(EventRequestFlags::BOTH_EDGES)
+    }
+
+    /// Create a second handle for the gpio by duplicating the fd
+    ///
+    /// Both handles share the same request and event queue, so each event is
+    /// only read by one of them. The gpio is only released once all of them
+    /// have been dropped.
+    pub fn try_clone(&self) -> io::Result<GpioEventHandle> {
+        Ok(GpioEventHandle {
+            file: self.file.try_clone()?,
+            gpio: self.gpio,
+            eventflags: self.eventflags,
+            handleflags: self.handleflags,
+            last_timestamp: AtomicU64::new(self.last_timestamp.load(Ordering::Relaxed)),
+            _audit: self._audit.clone(),
+            v2: self.v2,
+            clock: self.clock,
+            debounce: self.debounce,
+        })
+    }
+
+    /// Release the gpio, reporting errors of closing the fd
+    ///
+    /// Dropping the handle releases the gpio as well, but ignores errors. If
+    /// the handle has been cloned, the gpio stays requested until all clones
+    /// are gone.
+    pub fn close(self) -> io::Result<()> {
+        close_file(self.file)
+    }
+
+    /// Check if the handle's fd is still open
+    pub fn is_valid(&self) -> bool {
+        fd_is_valid(self.file.as_raw_fd())
+    }
+}
+
+impl GpioHandle {
+    /// Get GPIO value
+    pub fn get(&self) -> io::Result<u8> {
+        if self.v2 {
+            let mut values = ioctl::v2::gpio_v2_line_values { bits: 0, mask: 1 };
+
+            from_nix_result(unsafe {
+                ioctl::v2::get_line_values(self.file.as_raw_fd(), &mut values)
+            })?;
+
+            return Ok((values.bits & 1) as u8);
+        }
+
+        let mut data = ioctl::gpiohandle_data { values: [0; 64] };
+
+        from_nix_result(unsafe {
+            ioctl::get_line_values(self.file.as_raw_fd(), &mut data)
+        })?;
+
+        Ok(data.values[0])
+    }
+
+    /// Set GPIO value
+    pub fn set(&self, value: u8) -> io::Result<()> {
+        if self.v2 {
+            let mut values = ioctl::v2::gpio_v2_line_values { bits: (value & 1) as u64, mask: 1 };
+
+            from_nix_result(unsafe {
+                ioctl::v2::set_line_values(self.file.as_raw_fd(), &mut values)
+            })?;
+
+            return Ok(());
+        }
+
+        let mut data = ioctl::gpiohandle_data { values: [0; 64] };
+        data.values[0] = value;
+
+        from_nix_result(unsafe {
+            ioctl::set_line_values(self.file.as_raw_fd(), &mut data)
+        })?;
+
+        Ok(())
+    }
+
+    /// Get GPIO value as bool
+    pub fn get_bool(&self) -> io::Result<bool> {
+        Ok(self.get()? != 0)
+    }
+
+    /// Set GPIO value from a bool
+    pub fn set_bool(&self, value: bool) -> io::Result<()> {
+        self.set(value as u8)
+    }
+
+    /// Invert the GPIO value
+    ///
+    /// This reads back the current value and sets its inverse, so it is not
+    /// atomic. The logical value is flipped, active-low lines are inverted
+    /// by the kernel.
+    pub fn toggle(&self) -> io::Result<()> {
+        let value = self.get()?;
+
+        self.set(if value == 0 { 1 } else { 0 })
+    }
+
+    /// Set the GPIO to `value` for `duration` and then to the inverse value
+    ///
+    /// The duration is timed in userspace, so the pulse can be longer than
+    /// requested due to scheduling, easily by tens of microseconds or more.
+    /// `thread::sleep()` resumes sleeping when interrupted by signals, so the
+    /// pulse is never shorter than `duration`.
+    pub fn pulse(&self, value: u8, duration: Duration) -> io::Result<()> {
+        check_value(value)?;
+
+        self.set(value)?;
+        thread::sleep(duration);
+        self.set(1 - value)
+    }
+
+    /// Blink the GPIO `count` times, e.g. for status LEDs
+    ///
+    /// Each blink sets the GPIO to 1 for half of `period` and to 0 for the
+    /// other half. These are logical values, so active-low lines are
+    /// inverted by the kernel. Afterwards the GPIO is restored to the value
+    /// it had before. A `count` of 0 does nothing. Errors abort the blinking
+    /// immediately without restoring the value. The timing is done in
+    /// userspace like with `pulse()`.
+    pub fn blink(&self, count: u32, period: Duration) -> io::Result<()> {
+        if count == 0 {
+            return Ok(());
+        }
+
+        let value = self.get()?;
+        let half = period / 2;
+
+        for _ in 0..count {
+            self.set(1)?;
+            thread::sleep(half);
+            self.set(0)?;
+            thread::sleep(half);
+        }
+
+        self.set(value)
+    }
+
+    /// Change the configuration of the requested gpio
+    ///
+    /// This allows e.g. switching the direction, active-low or bias without
+    /// releasing the gpio. `default` is the value driven after switching to
+    /// output mode and is ignored when switching to input mode. Requires
+    /// Linux 5.5 or newer.
+    ///
+    /// Handles requested with `request_debounced()` use the v2 API and can
+    /// not be reconfigured, as this would drop the debounce period. They
+    /// return an `Unsupported` error.
+    pub fn set_config(&mut self, flags: RequestFlags, default: u8) -> io::Result<()> {
+        if self.v2 {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "reconfiguring debounced gpios is not supported"));
+        }
+
+        check_flags(flags)?;
+        check_value(default)?;
+
+        let mut config = ioctl::gpiohandle_config { flags: flags.bits, default_values: [0; 64], padding: [0; 4] };
+        config.default_values[0] = default;
+
+        from_nix_result(unsafe {
+            ioctl::set_config(self.file.as_raw_fd(), &mut config)
+        })?;
+
+        self.flags = flags;
+        Ok(())
+    }
+
+    /// Switch the requested gpio between input and output mode
+    ///
+    /// The other flags are kept, except that open-drain and open-source are
+    /// dropped when switching to input mode. `default` is the value driven
+    /// after switching to output mode and is ignored when switching to input
+    /// mode. Like `set_config()` this requires Linux 5.5 or newer.
+    pub fn set_direction(&mut self, output: bool, default: u8) -> io::Result<()> {
+        self.set_config(direction_flags(self.flags, output), default)
+    }
+
+    /// Get GPIO value as a single element slice of values
+    ///
+    /// This matches the representation used by `GpioArrayHandle`, so code
+    /// can treat single and multiple gpios the same way.
+    pub fn get_values(&self) -> io::Result<Vec<u8>> {
+        Ok(vec![self.get()?])
+    }
+
+    /// Set GPIO value from a single element slice of values
+    pub fn set_values(&self, values: &[u8]) -> io::Result<()> {
+        if values.len() != 1 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid amount of values"));
+        }
+
+        self.set(values[0])
+    }
+
+    /// Create a second handle for the gpio by duplicating the fd
+    ///
+    /// Both handles share the same request, so the gpio is only released
+    /// once all of them have been dropped.
+    pub fn try_clone(&self) -> io::Result<GpioHandle> {
+        Ok(GpioHandle {file: self.file.try_clone()?, gpio: self.gpio, consumer: self.consumer.clone(), flags: self.flags, _audit: self._audit.clone(), v2: self.v2})
+    }
+
+    /// Release the gpio, reporting errors of closing the fd
+    ///
+    /// Dropping the handle releases the gpio as well, but ignores errors. If
+    /// the handle has been cloned, the gpio stays requested until all clones
+    /// are gone.
+    pub fn close(self) -> io::Result<()> {
+        close_file(self.file)
+    }
+
+    /// Check if the handle's fd is still open
+    pub fn is_valid(&self) -> bool {
+        fd_is_valid(self.file.as_raw_fd())
+    }
+
+    /// Read the consumer label, that the kernel recorded for this gpio
+    ///
+    /// `chip` must be the chip the handle has been requested from. This can
+    /// differ from `self.consumer`, e.g. if the label got truncated.
+    pub fn kernel_consumer(&self, chip: &GpioChip) -> io::Result<Option<String>> {
+        Ok(chip.info(self.gpio)?.consumer)
+    }
+
+    /// Get the direction the gpio has been requested or configured with
+    ///
+    /// This is taken from `self.flags` without asking the kernel, as a line
+    /// handle can not query the line info. `None` is returned if the gpio
+    /// was requested without direction, keeping the kernel's setting. Use
+    /// `kernel_direction()` to read the direction from the chip.
+    pub fn direction(&self) -> Option<Direction> {
+        if self.flags.contains(RequestFlags::OUTPUT) {
+            Some(Direction::Output)
+        } else if self.flags.contains(RequestFlags::INPUT) {
+            Some(Direction::Input)
+        } else {
+            None
+        }
+    }
+
+    /// Read the direction, that the kernel reports for this gpio
+    ///
+    /// `chip` must be the chip the handle has been requested from.
+    pub fn kernel_direction(&self, chip: &GpioChip) -> io::Result<Direction> {
+        if chip.info(self.gpio)?.flags.is_output() {
+            Ok(Direction::Output)
+        } else {
+            Ok(Direction::Input)
+        }
+    }
+
+    /// Get GPIO value by majority vote
+    ///
+    /// The value is read `samples` times and returned, if it was read more
+    /// often than the other value and at least `agree` times. Otherwise, also
+    /// on a tie, an `InvalidData` error is returned. Each
+    /// sample is a separate syscall, so this adds latency, which can be
+    /// significant for gpios behind a slow bus like I2C.
+    pub fn get_stable(&self, samples: usize, agree: usize) -> io::Result<u8> {
+        if agree == 0 || agree > samples {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "agree must be between 1 and samples"));
+        }
+
+        let mut high = 0;
+        for _ in 0..samples {
+            if self.get()? != 0 {
+                high += 1;
+            }
+        }
+
+        majority_vote(samples, high, agree)
+    }
+}
+
+impl GpioLinesV2 {
+    /// Check, that `mask` only contains bits for requested lines
+    fn check_mask(&self, mask: u64) -> io::Result<()> {
+        if self.offsets.len() < 64 && mask >> self.offsets.len() != 0 {
+            return Err(GpioError::IndexOutOfRange { index: 63 - mask.leading_zeros() as usize, len: self.offsets.len() }.into());
+        }
+
+        Ok(())
+    }
+
+    /// Get the values of the lines selected by `mask`
+    ///
+    /// Bit i of `mask` and the result correspond to `self.offsets[i]`.
+    pub fn get(&self, mask: u64) -> io::Result<u64> {
+        let mut values = ioctl::v2::gpio_v2_line_values { bits: 0, mask };
+        self.check_mask(mask)?;
+
+        from_nix_result(unsafe {
+            ioctl::v2::get_line_values(self.file.as_raw_fd(), &mut values)
+        })?;
+
+        Ok(values.bits & mask)
+    }
+
+    /// Set the values of the lines selected by `mask` to the according bits of `bits`
+    ///
+    /// Bit i of `mask` and `bits` correspond to `self.offsets[i]`.
+    pub fn set(&self, mask: u64, bits: u64) -> io::Result<()> {
+        let mut values = ioctl::v2::gpio_v2_line_values { bits: bits & mask, mask };
+        self.check_mask(mask)?;
+
+        from_nix_result(unsafe {
+            ioctl::v2::set_line_values(self.file.as_raw_fd(), &mut values)
+        })?;
+
+        Ok(())
+    }
+
+    /// Check if the handle's fd is still open
+    pub fn is_valid(&self) -> bool {
+        fd_is_valid(self.file.as_raw_fd())
+    }
+}
+
+impl GpioArrayHandle {
+    /// Get GPIO values
+    pub fn get(&self) -> io::Result<[u8; 64]> {
+        let mut data = ioctl::gpiohandle_data { values: [0; 64] };
+
+        from_nix_result(unsafe {
+            ioctl::get_line_values(self.file.as_raw_fd(), &mut data)
+        })?;
+
+        Ok(data.values)
+    }
+
+    /// Get GPIO values of the requested gpios only
+    ///
+    /// Unlike `get()`, the result contains one value per entry of `gpios`.
+    pub fn get_values(&self) -> io::Result<Vec<u8>> {
+        Ok(self.get()?[..self.gpios.len()].to_vec())
+    }
+
+    /// Get GPIO values of the requested gpios as booleans
+    pub fn get_bools(&self) -> io::Result<Vec<bool>> {
+        Ok(self.get()?[..self.gpios.len()].iter().map(|&value| value != 0).collect())
+    }
+
+    /// Set GPIO values
+    pub fn set(&self, values: &[u8]) -> io::Result<()> {
+        let mut data = ioctl::gpiohandle_data { values: [0; 64] };
+
+        if values.len() != self.gpios.len() || values.len() > 64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid amount of values"));
+        }
+
+        data.values[..values.len()].copy_from_slice(values);
+
+        from_nix_result(unsafe {
+            ioctl::set_line_values(self.file.as_raw_fd(), &mut data)
+        })?;
+
+        Ok(())
+    }
+
+    /// Read the GPIO values, let `f` modify them and write them back
+    ///
+    /// `f` gets one value per entry of `gpios`. Reading and writing are two
+    /// separate ioctls, so this is not atomic: a value changed by someone
+    /// else in between is overwritten. For outputs only driven through this
+    /// handle, the values read back are the ones set before, so nothing is
+    /// lost as long as the handle is not used from multiple threads at once.
+    pub fn modify<F: FnOnce(&mut [u8])>(&self, f: F) -> io::Result<()> {
+        let mut values = self.get_values()?;
+
+        f(&mut values);
+        self.set(&values)
+    }
+
+    /// Get GPIO values as bitmap, bit i is the value of `gpios[i]`
+    pub fn get_bitmap(&self) -> io::Result<u64> {
+        let values = self.get()?;
+
+        Ok(values[..self.gpios.len()].iter().enumerate().fold(0, |bits, (i, &value)| bits | ((value as u64 & 1) << i)))
+    }
+
+    /// Set GPIO values from a bitmap, bit i is the value of `gpios[i]`
+    ///
+    /// Bits beyond the amount of requested gpios must not be set.
+    pub fn set_bitmap(&self, bits: u64) -> io::Result<()> {
+        let count = self.gpios.len();
+
+        if count < 64 && bits >> count != 0 {
+            return Err(GpioError::IndexOutOfRange { index: 63 - bits.leading_zeros() as usize, len: count }.into());
+        }
+
+        let values: Vec<u8> = (0..count).map(|i| (bits >> i & 1) as u8).collect();
+        self.set(&values)
+    }
+
+    /// Create a second handle for the gpios by duplicating the fd
+    ///
+    /// Both handles share the same request, so the gpios are only released
+    /// once all of them have been dropped.
+    pub fn try_clone(&self) -> io::Result<GpioArrayHandle> {
+        Ok(GpioArrayHandle {file: self.file.try_clone()?, gpios: self.gpios.clone(), consumer: self.consumer.clone(), flags: self.flags, names: self.names.clone(), _audit: self._audit.clone()})
+    }
+
+    /// Release the gpios, reporting errors of closing the fd
+    ///
+    /// Dropping the handle releases the gpios as well, but ignores errors. If
+    /// the handle has been cloned, the gpios stay requested until all clones
+    /// are gone.
+    pub fn close(self) -> io::Result<()> {
+        close_file(self.file)
+    }
+
+    /// Check if the handle's fd is still open
+    pub fn is_valid(&self) -> bool {
+        fd_is_valid(self.file.as_raw_fd())
+    }
+
+    /// Get GPIO values mapped by gpio number
+    pub fn get_map(&self) -> io::Result<HashMap<u32, u8>> {
+        let values = self.get()?;
+
+        Ok(self.gpios.iter().cloned().zip(values.iter().cloned()).collect())
+    }
+
+    /// Get the names of the gpios captured when requesting them
+    ///
+    /// The names are in the order of `gpios`, unnamed gpios are `None`.
+    pub fn names(&self) -> &[Option<String>] {
+        &self.names
+    }
+
+    /// Get GPIO values mapped by gpio name
+    ///
+    /// Unnamed gpios are left out. If multiple gpios share a name, the value
+    /// of the first one is returned.
+    pub fn get_named(&self) -> io::Result<HashMap<String, u8>> {
+        let values = self.get()?;
+        let mut result = HashMap::new();
+
+        for (name, &value) in self.names.iter().zip(values.iter()) {
+            if let Some(ref name) = *name {
+                result.entry(name.clone()).or_insert(value);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Set the values of the gpios with the names in `values`
+    ///
+    /// Gpios not contained in `values` keep their current value, so this
+    /// reads the values first. All gpios sharing a name are set. Unknown
+    /// names are rejected with `InvalidInput`.
+    pub fn set_named(&self, values: &HashMap<String, u8>) -> io::Result<()> {
+        for (name, &value) in values {
+            if !self.names.iter().any(|n| n.as_ref() == Some(name)) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("gpio {:?} not in array", name)));
+            }
+            check_value(value)?;
+        }
+
+        let mut current = self.get_values()?;
+        for (i, name) in self.names.iter().enumerate() {
+            if let Some(&value) = name.as_ref().and_then(|name| values.get(name)) {
+                current[i] = value;
+            }
+        }
+
+        self.set(&current)
+    }
+
+    /// Swap the values of the gpios at index `i` and `j`
+    ///
+    /// The current values are read and written back with both values swapped
+    /// in a single set operation.
+    pub fn swap(&self, i: usize, j: usize) -> io::Result<()> {
+        let count = self.gpios.len();
+
+        if i >= count || j >= count {
+            return Err(GpioError::IndexOutOfRange { index: i.max(j), len: count }.into());
+        }
+
+        let mut values = self.get()?;
+        values.swap(i, j);
+
+        self.set(&values[..count])
+    }
+}
+
+impl GpioChip {
+    /// Acquire information about the gpiochip
+    ///
+    /// Returns an Error or (name, label, number_of_gpios)
+    fn chipinfo(fd: RawFd) -> io::Result<(String, String, u32)> {
+        let mut info = ioctl::gpiochip_info { name: [0; 32], label: [0; 32], lines: 0 };
+
+        from_nix_result(unsafe {
+            ioctl::get_chipinfo(fd, &mut info)
+        })?;
+
+        let name = unsafe {CStr::from_ptr(info.name.as_ptr())}.to_string_lossy().into_owned();
+        let label = unsafe {CStr::from_ptr(info.label.as_ptr())}.to_string_lossy().into_owned();
+
+        Ok((name, label, info.lines))
+    }
+
+    /// Open the gpiochip with the provided path
+    ///
+    /// Typically, the path will be something like `"/dev/gpiochip0"`.
+    ///
+    /// The chip is opened close-on-exec, so it does not leak into spawned
+    /// processes. The fds of requested gpios are always created close-on-exec
+    /// by the kernel.
+    pub fn new<P: AsRef<std::path::Path>>(path: P) -> io::Result<GpioChip> {
+        let file = open_cloexec(path.as_ref())?;
+        let (name, label, lines) = GpioChip::chipinfo(file.as_raw_fd())?;
+
+        Ok(GpioChip {file, name, label, lines, audit: None})
+    }
+
+    /// Create a `GpioChip` from an already opened fd
+    ///
+    /// Returns an error if the fd does not refer to a gpiochip.
+    pub fn from_fd(fd: OwnedFd) -> io::Result<GpioChip> {
+        let file = std::fs::File::from(fd);
+        let (name, label, lines) = GpioChip::chipinfo(file.as_raw_fd())?;
+
+        Ok(GpioChip {file, name, label, lines, audit: None})
+    }
+
+    /// Open all `/dev/gpiochip*` devices sorted by chip number
+    ///
+    /// Chips, that can not be opened due to missing permissions or that
+    /// disappeared while scanning, are skipped. Other errors are returned.
+    pub fn enumerate() -> io::Result<Vec<GpioChip>> {
+        let mut chips = Vec::new();
+
+        for path in chip_paths()? {
+            match GpioChip::new(&path) {
+                Ok(chip) => chips.push(chip),
+                Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied || err.kind() == io::ErrorKind::NotFound => {},
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(chips)
+    }
+
+    /// Open the first gpiochip with the provided label
+    ///
+    /// Unlike the chip numbers, labels (e.g. `"pinctrl-bcm2711"`) are stable
+    /// across boots and kernel versions. Returns `NotFound`, if no accessible
+    /// chip has the label.
+    pub fn open_by_label(label: &str) -> io::Result<GpioChip> {
+        for path in chip_paths()? {
+            match GpioChip::new(&path) {
+                Ok(chip) if chip.label == label => return Ok(chip),
+                Ok(_) => {},
+                Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied || err.kind() == io::ErrorKind::NotFound => {},
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(io::Error::new(io::ErrorKind::NotFound, format!("no gpiochip with label {:?}", label)))
+    }
+
+    /// Check, that `gpio` is provided by the chip
+    fn check_gpio(&self, gpio: u32) -> io::Result<()> {
+        if gpio >= self.lines {
+            return Err(GpioError::OffsetOutOfRange { offset: gpio, lines: self.lines }.into());
+        }
+
+        Ok(())
+    }
+
+    /// Register a hook, that is called whenever gpios are requested from
+    /// this chip and when the resulting handles are dropped
+    pub fn set_audit_hook<F: Fn(&AuditRecord) + Send + Sync + 'static>(&mut self, hook: F) {
+        self.audit = Some(Arc::new(hook));
+    }
+
+    /// Report acquired gpios to the audit hook and create the token for their release
+    fn audit_acquired(&self, consumer: &str, gpios: &[u32]) -> Option<Arc<AuditToken>> {
+        self.audit.as_ref().map(|hook| {
+            let token = AuditToken {hook: hook.clone(), consumer: consumer.to_string(), gpios: gpios.to_vec(), detached: AtomicBool::new(false)};
+            token.report(AuditAction::Acquired);
+            Arc::new(token)
+        })
+    }
+
+    /// Acquire information about a gpio
+    pub fn info(&self, gpio: u32) -> io::Result<LineInfo> {
+        let mut info = ioctl::gpioline_info { line_offset: 0, flags: 0, name: [0; 32], consumer: [0; 32] };
+        info.line_offset = gpio;
+
+        from_nix_result(unsafe {
+            ioctl::get_lineinfo(self.file.as_raw_fd(), &mut info)
+        })?;
+
+        Ok(LineInfo::from_raw(&info))
+    }
+
+    /// Iterate over the information of all gpios
+    ///
+    /// The information is acquired lazily, one gpio per iteration step.
+    pub fn lines_iter(&self) -> impl Iterator<Item = io::Result<LineInfo>> + '_ {
+        (0..self.lines).map(move |gpio| self.info(gpio))
+    }
+
+    /// Acquire information about all gpios
+    pub fn all_line_info(&self) -> io::Result<Vec<LineInfo>> {
+        self.lines_iter().collect()
+    }
+
+    /// Acquire information about all gpios using the v2 API
+    ///
+    /// The kernel has no ioctl returning the information of multiple lines,
+    /// so this still takes one syscall per line and is not faster than
+    /// `all_line_info()`. It exists for code, that wants to rely on the v2
+    /// API only. On kernels without the v2 API it falls back to
+    /// `all_line_info()`.
+    pub fn all_line_info_v2(&self) -> io::Result<Vec<LineInfo>> {
+        let mut line_info = Vec::with_capacity(self.lines as usize);
+
+        for gpio in 0..self.lines {
+            let mut info: ioctl::v2::gpio_v2_line_info = unsafe { std::mem::zeroed() };
+            info.offset = gpio;
+
+            match from_v2_result(unsafe { ioctl::v2::get_line_info(self.file.as_raw_fd(), &mut info) }) {
+                Ok(_) => line_info.push(LineInfo::from_raw_v2(&info)),
+                Err(ref err) if err.kind() == io::ErrorKind::Unsupported && gpio == 0 => return self.all_line_info(),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(line_info)
+    }
+
+    /// Watch a gpio for changes of its line info
+    ///
+    /// Changes are reported on the chip fd and can be read using
+    /// `read_line_info_event()`. Requires Linux 5.7 or newer.
+    pub fn watch_line(&self, gpio: u32) -> io::Result<()> {
+        let mut info = ioctl::gpioline_info { line_offset: gpio, flags: 0, name: [0; 32], consumer: [0; 32] };
+
+        from_nix_result(unsafe {
+            ioctl::watch_lineinfo(self.file.as_raw_fd(), &mut info)
+        })?;
+
+        Ok(())
+    }
+
+    /// Stop watching a gpio for changes of its line info
+    pub fn unwatch_line(&self, gpio: u32) -> io::Result<()> {
+        let mut offset = gpio;
+
+        from_nix_result(unsafe {
+            ioctl::unwatch_lineinfo(self.file.as_raw_fd(), &mut offset)
+        })?;
+
+        Ok(())
+    }
+
+    /// Wait until a line info change is available or timeout occured
+    ///
+    /// Returns true if a change can be read with `read_line_info_event()`.
+    pub fn wait_for_line_info_event(&self, timeout_ms: i32) -> io::Result<bool> {
+        let mut fd = libc::pollfd { fd: self.file.as_raw_fd(), events: libc::POLLIN | libc::POLLPRI, revents: 0 };
+
+        let ret = unsafe { libc::poll(&mut fd, 1, timeout_ms) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(fd.revents != 0)
+    }
+
+    /// Read a change of a watched gpio, blocking until one is available
+    pub fn read_line_info_event(&self) -> io::Result<LineInfoChange> {
+        let mut buf = [0u8; std::mem::size_of::<ioctl::gpioline_info_changed>()];
+        let size = from_nix_result(nix::unistd::read(self.file.as_raw_fd(), &mut buf))?;
+
+        LineInfoChange::from_raw(&buf[..size])
+    }
+
+    /// Watch multiple gpios for changes of their line info
+    ///
+    /// If watching one of the gpios fails, the gpios watched so far are
+    /// unwatched again and the error is returned.
+    pub fn watch_lines(&self, gpios: &[u32]) -> io::Result<()> {
+        for (i, &gpio) in gpios.iter().enumerate() {
+            if let Err(err) = self.watch_line(gpio) {
+                for &watched in &gpios[..i] {
+                    let _ = self.unwatch_line(watched);
+                }
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read all queued changes of watched gpios without blocking
+    ///
+    /// The changes of all watched gpios are reported in the order they
+    /// occurred. Use the `gpio` field of their info to tell them apart.
+    pub fn read_line_info_events(&self) -> io::Result<Vec<LineInfoChange>> {
+        const RECORDS_PER_READ: usize = 16;
+        let record = std::mem::size_of::<ioctl::gpioline_info_changed>();
+        let mut buf = vec![0u8; RECORDS_PER_READ * record];
+        let mut changes = Vec::new();
+
+        while self.wait_for_line_info_event(0)? {
+            let size = from_nix_result(nix::unistd::read(self.file.as_raw_fd(), &mut buf))?;
+
+            for raw in buf[..size].chunks(record) {
+                changes.push(LineInfoChange::from_raw(raw)?);
+            }
+        }
+
+        Ok(changes)
+    }
+
+    /// Probe the features supported for this chip
+    ///
+    /// The v2 uAPI is detected by issuing its line info ioctl. The other
+    /// features can not be probed without requesting lines, which could
+    /// change their configuration. They are derived from the kernel version
+    /// and the line info of all gpios instead.
+    pub fn capabilities(&self) -> io::Result<Capabilities> {
+        let mut v2 = self.lines > 0;
+        let mut line_flags = LineFlags::empty();
+
+        for gpio in 0..self.lines {
+            let mut info: ioctl::v2::gpio_v2_line_info = unsafe { std::mem::zeroed() };
+            info.offset = gpio;
+
+            match from_v2_result(unsafe { ioctl::v2::get_line_info(self.file.as_raw_fd(), &mut info) }) {
+                Ok(_) => line_flags |= LineFlags::from_bits_truncate(info.flags),
+                Err(ref err) if err.kind() == io::ErrorKind::Unsupported => {
+                    v2 = false;
+                    break;
+                },
+                Err(err) => return Err(err),
+            }
+        }
+
+        let version = kernel_version().unwrap_or((0, 0));
+
+        let mut event_clocks = vec![EventClock::Monotonic];
+        if v2 && (version >= (5, 11) || line_flags.contains(LineFlags::EVENT_CLOCK_REALTIME)) {
+            event_clocks.push(EventClock::Realtime);
+        }
+        if line_flags.contains(LineFlags::EVENT_CLOCK_HTE) {
+            event_clocks.push(EventClock::Hardware);
+        }
+
+        Ok(Capabilities {
+            lines: self.lines,
+            v2,
+            bias: v2 || version >= (5, 5),
+            debounce: v2,
+            open_drain: true,
+            open_source: true,
+            event_clocks,
+        })
+    }
+
+    /// Acquire information about the chip and all of its gpios
+    pub fn report(&self) -> io::Result<ChipReport> {
+        let line_info = self.all_line_info()?;
+
+        Ok(ChipReport {name: self.name.clone(), label: self.label.clone(), lines: self.lines, line_info})
+    }
+
+    /// Read the values of all gpios together with a `CLOCK_MONOTONIC` timestamp
+    ///
+    /// All gpios are briefly requested in chunks of 64, without changing their
+    /// direction, and the timestamp is taken right before reading them. If any
+    /// gpio is in use, the request fails with `EBUSY`.
+    pub fn timestamped_snapshot(&self, consumer: &str) -> io::Result<(Duration, Vec<bool>)> {
+        let gpios: Vec<u32> = (0..self.lines).collect();
+        let mut handles = Vec::new();
+        let mut values = Vec::with_capacity(gpios.len());
+
+        for chunk in gpios.chunks(64) {
+            handles.push(self.request_array(consumer, RequestFlags::empty(), chunk, &vec![0; chunk.len()])?);
+        }
+
+        let timestamp = Duration::from_nanos(monotonic_now()?);
+        for handle in &handles {
+            let data = handle.get()?;
+            values.extend(data[..handle.gpios.len()].iter().map(|&v| v != 0));
+        }
+
+        Ok((timestamp, values))
+    }
+
+    /// Capture the current configuration of a gpio
+    ///
+    /// The direction, drive and bias flags are taken from `info()`. For an
+    /// unused output the driven value is read back by briefly requesting the
+    /// line as `consumer` without changing its direction, otherwise `default`
+    /// is 0. This request is reported to the audit hook like any other and
+    /// fails with `EBUSY`, if the line gets requested by someone else
+    /// meanwhile.
+    pub fn capture_config(&self, gpio: u32, consumer: &str) -> io::Result<LineConfig> {
+        let info = self.info(gpio)?;
+        let mut flags = RequestFlags::empty();
+        let mut default = 0;
+
+        if info.flags.contains(Flags::ACTIVE_LOW) {
+            flags |= RequestFlags::ACTIVE_LOW;
+        }
+        if info.flags.contains(Flags::OPEN_DRAIN) {
+            flags |= RequestFlags::OPEN_DRAIN;
+        }
+        if info.flags.contains(Flags::OPEN_SOURCE) {
+            flags |= RequestFlags::OPEN_SOURCE;
+        }
+        if info.flags.contains(Flags::BIAS_PULL_UP) {
+            flags |= RequestFlags::BIAS_PULL_UP;
+        }
+        if info.flags.contains(Flags::BIAS_PULL_DOWN) {
+            flags |= RequestFlags::BIAS_PULL_DOWN;
+        }
+        if info.flags.contains(Flags::BIAS_DISABLE) {
+            flags |= RequestFlags::BIAS_DISABLE;
+        }
+
+        if info.flags.contains(Flags::OUTPUT) {
+            if !info.flags.contains(Flags::KERNEL) {
+                let handle = self.request(consumer, flags & RequestFlags::ACTIVE_LOW, gpio, 0)?;
+                default = handle.get()?;
+            }
+            flags |= RequestFlags::OUTPUT;
+        } else {
+            flags |= RequestFlags::INPUT;
+        }
+
+        Ok(LineConfig {flags, default})
+    }
+
+    /// Request a `GpioHandle` configured according to a captured `LineConfig`
+    ///
+    /// The kernel only applies a configuration when the line is requested, so
+    /// restoring requires any handle that reconfigured the line to be dropped
+    /// first. The line keeps the restored configuration while the returned
+    /// handle is held.
+    pub fn apply_config(&self, gpio: u32, config: &LineConfig, consumer: &str) -> io::Result<GpioHandle> {
+        self.request(consumer, config.flags, gpio, config.default)
+    }
+
+    /// Find all gpios with a name matching `pattern`
+    ///
+    /// The pattern is a simple glob, in which `*` matches any sequence of
+    /// characters and `?` matches a single character, e.g. `"led-*"`.
+    /// Unnamed gpios are matched as empty name.
+    pub fn find_lines_matching(&self, pattern: &str) -> io::Result<Vec<u32>> {
+        let mut gpios = Vec::new();
+
+        for gpio in 0..self.lines {
+            if glob_match(pattern, self.info(gpio)?.name.as_deref().unwrap_or("")) {
+                gpios.push(gpio);
+            }
+        }
+
+        Ok(gpios)
+    }
+
+    /// Find a gpio by its name
+    ///
+    /// If multiple gpios share the name, the lowest offset is returned.
+    pub fn find_line(&self, name: &str) -> io::Result<Option<u32>> {
+        for gpio in 0..self.lines {
+            if self.info(gpio)?.name.as_deref() == Some(name) {
+                return Ok(Some(gpio));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Map the names of all named gpios to their number, keeping the first of duplicates
+    fn line_numbers(&self) -> io::Result<HashMap<String, u32>> {
+        let mut numbers = HashMap::new();
+
+        for gpio in (0..self.lines).rev() {
+            if let Some(name) = self.info(gpio)?.name {
+                numbers.insert(name, gpio);
+            }
+        }
+
+        Ok(numbers)
+    }
+
+    /// Check if a request for a single gpio would succeed, without requesting it
+    ///
+    /// This validates the gpio number and flags and checks, that the gpio is
+    /// not in use (`EBUSY` otherwise). The gpio can still get requested by
+    /// somebody else between this check and an actual request.
+    pub fn validate_request(&self, flags: RequestFlags, gpio: u32) -> io::Result<()> {
+        self.check_gpio(gpio)?;
+        check_flags(flags)?;
+
+        if self.info(gpio)?.flags.contains(Flags::KERNEL) {
+            return Err(io::Error::from_raw_os_error(libc::EBUSY));
+        }
+
+        Ok(())
+    }
+
+    /// Request a `GpioHandle` for a single gpio
+    pub fn request(&self, consumer: &str, flags: RequestFlags, gpio: u32, default: u8) -> io::Result<GpioHandle> {
+        let mut request = ioctl::gpiohandle_request { lineoffsets: [0; 64], flags: 0, default_values: [0; 64], consumer_label: [0; 32], lines: 0, fd: 0 };
+
+        self.check_gpio(gpio)?;
+        check_flags(flags)?;
+        copy_label(&mut request.consumer_label, consumer)?;
+        check_value(default)?;
+
+        request.lineoffsets[0] = gpio;
+        request.flags = flags.bits;
+        request.default_values[0] = default;
+        request.lines = 1;
+
+        from_nix_result(unsafe {
+            ioctl::get_linehandle(self.file.as_raw_fd(), &mut request)
+        })?;
+
+        Ok(GpioHandle {file: unsafe {std::fs::File::from_raw_fd(request.fd)}, consumer: consumer.to_string(), flags, gpio, _audit: self.audit_acquired(consumer, &[gpio]), v2: false})
+    }
+
+    /// Request a `GpioHandle` for an output and verify its value
+    ///
+    /// After requesting the gpio as output driving `level`, the value is read
+    /// back and an error is returned if it does not match. Note that for most
+    /// chips reading an output returns the driven value, not the physical
+    /// level, so this catches misconfiguration but not every hardware fault.
+    pub fn request_output_verified(&self, consumer: &str, gpio: u32, level: u8) -> io::Result<GpioHandle> {
+        let handle = self.request(consumer, RequestFlags::OUTPUT, gpio, level)?;
+
+        if handle.get()? != level {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("gpio {} did not reach level {}", gpio, level)));
+        }
+
+        Ok(handle)
+    }
+
+    /// Request a `GpioHandle` for each of the named gpios
+    ///
+    /// The handles are returned mapped by name. If a name can not be found or
+    /// one of the requests fails, an error mentioning the name is returned and
+    /// all gpios requested so far are released. Errors carrying a `GpioError`
+    /// are returned unchanged, so they can still be converted back.
+    pub fn request_by_names(&self, consumer: &str, flags: RequestFlags, names: &[&str], default_values: &[u8]) -> io::Result<HashMap<String, GpioHandle>> {
+        if names.len() != default_values.len() {
+            return Err(GpioError::ValueCountMismatch { expected: names.len(), got: default_values.len() }.into());
+        }
+
+        let numbers = self.line_numbers()?;
+        let mut handles = HashMap::with_capacity(names.len());
+
+        for (name, default) in names.iter().zip(default_values) {
+            let gpio = match numbers.get(*name) {
+                Some(gpio) => *gpio,
+                None => return Err(io::Error::new(io::ErrorKind::NotFound, format!("gpio {:?} not found", name))),
+            };
+
+            let handle = self.request(consumer, flags, gpio, *default)
+                .map_err(|err| name_context(err, name))?;
+            handles.insert(name.to_string(), handle);
+        }
+
+        Ok(handles)
+    }
+
+    /// Request a `GpioHandle` for a named gpio
+    ///
+    /// The gpio is looked up with `find_line()`, so the first of multiple
+    /// gpios sharing the name is requested.
+    pub fn request_by_name(&self, consumer: &str, flags: RequestFlags, name: &str, default: u8) -> io::Result<GpioHandle> {
+        let gpio = match self.find_line(name)? {
+            Some(gpio) => gpio,
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, format!("gpio {:?} not found", name))),
+        };
+
+        self.request(consumer, flags, gpio, default)
+            .map_err(|err| name_context(err, name))
+    }
+
+    /// Request a `GpioHandle` for a named gpio, inferring active-low from the name
+    ///
+    /// With `active_low` set to `None`, `RequestFlags::ACTIVE_LOW` is added
+    /// to `base_flags`, if the name is recognized as active-low by
+    /// `infers_active_low()`. `Some(true)` or `Some(false)` overrides the
+    /// inference and sets or clears `RequestFlags::ACTIVE_LOW` explicitly.
+    pub fn request_by_name_smart(&self, consumer: &str, base_flags: RequestFlags, name: &str, default: u8, active_low: Option<bool>) -> io::Result<GpioHandle> {
+        self.request_by_name(consumer, smart_flags(base_flags, name, active_low), name, default)
+    }
+
+    /// Request gpios as outputs driving a safe level
+    ///
+    /// `levels` maps the gpios to their safe level. They are requested one
+    /// after another in ascending order, so each one is driven as soon as it
+    /// is requested. If a gpio is in use, `EBUSY` is returned and the gpios
+    /// requested so far are released again. Dropping the returned handles
+    /// releases the gpios, after which most chips keep driving the level.
+    pub fn request_safe_defaults(&self, consumer: &str, levels: &BTreeMap<u32, u8>) -> io::Result<BTreeMap<u32, GpioHandle>> {
+        let mut handles = BTreeMap::new();
+
+        for (&gpio, &level) in levels {
+            handles.insert(gpio, self.request(consumer, RequestFlags::OUTPUT, gpio, level)?);
+        }
+
+        Ok(handles)
+    }
+
+    /// Request a `GpioArrayHandle` for multiple gpios, that should be get/set simultaneously
+    pub fn request_array(&self, consumer: &str, flags: RequestFlags, gpios: &[u32], default_values: &[u8]) -> io::Result<GpioArrayHandle> {
+        let mut request = ioctl::gpiohandle_request { lineoffsets: [0; 64], flags: 0, default_values: [0; 64], consumer_label: [0; 32], lines: 0, fd: 0 };
+        let mut vec: std::vec::Vec<u32> = std::vec::Vec::with_capacity(gpios.len());
+
+        if gpios.len() > request.lineoffsets.len() {
+            return Err(GpioError::TooManyLines.into());
+        }
+
+        if gpios.len() != default_values.len() {
+            return Err(GpioError::ValueCountMismatch { expected: gpios.len(), got: default_values.len() }.into());
+        }
+
+        for &gpio in gpios {
+            self.check_gpio(gpio)?;
+        }
+        for &default in default_values {
+            check_value(default)?;
+        }
+        check_flags(flags)?;
+        copy_label(&mut request.consumer_label, consumer)?;
+
+        request.flags = flags.bits;
+        request.lines = gpios.len() as u32;
+
+        for x in 0..gpios.len() {
+            request.lineoffsets[x] = gpios[x];
+            request.default_values[x] = default_values[x];
+            vec.push(gpios[x]);
+        }
+
+        let names = gpios.iter().map(|&gpio| Ok(self.info(gpio)?.name)).collect::<io::Result<Vec<_>>>()?;
+
+        from_nix_result(unsafe {
+            ioctl::get_linehandle(self.file.as_raw_fd(), &mut request)
+        })?;
+
+        Ok(GpioArrayHandle {file: unsafe {std::fs::File::from_raw_fd(request.fd)}, consumer: consumer.to_string(), flags, names: names.into_boxed_slice(), _audit: self.audit_acquired(consumer, &vec), gpios: vec.into_boxed_slice()})
+    }
+
+    /// Request a `GpioArrayHandle` for multiple gpios, that all use the same default value
+    pub fn request_array_uniform(&self, consumer: &str, flags: RequestFlags, gpios: &[u32], default: u8) -> io::Result<GpioArrayHandle> {
+        check_value(default)?;
+
+        self.request_array(consumer, flags, gpios, &vec![default; gpios.len()])
+    }
+
+    /// Run `f` on a duplicate of the chip in a separate thread, bounded by `timeout`
+    ///
+    /// If the timeout passes, `TimedOut` is returned. The thread keeps running
+    /// until `f` returns and drops its result, so e.g. handles requested too
+    /// late are released again.
+    fn with_timeout<T, F>(&self, timeout: Duration, f: F) -> io::Result<T>
+        where T: Send + 'static, F: FnOnce(&GpioChip) -> io::Result<T> + Send + 'static
+    {
+        let chip = GpioChip {file: self.file.try_clone()?, name: self.name.clone(), label: self.label.clone(), lines: self.lines, audit: self.audit.clone()};
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        thread::Builder::new().name("gpio-request".to_string()).spawn(move || {
+            let _ = sender.send(f(&chip));
+        })?;
+
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "request timed out")),
+        }
+    }
+
+    /// Request a `GpioArrayHandle` with the request bounded by `timeout`
+    ///
+    /// Requests for gpios of SoC-native chips finish effectively instantly.
+    /// The timeout mainly guards chips behind slow or contended buses, like
+    /// I2C GPIO expanders. If it passes, `TimedOut` is returned and the gpios
+    /// are released once the kernel completes the request.
+    pub fn request_array_timeout(&self, consumer: &str, flags: RequestFlags, gpios: &[u32], default_values: &[u8], timeout: Duration) -> io::Result<GpioArrayHandle> {
+        let consumer = consumer.to_string();
+        let gpios = gpios.to_vec();
+        let default_values = default_values.to_vec();
+
+        self.with_timeout(timeout, move |chip| chip.request_array(&consumer, flags, &gpios, &default_values))
+    }
+
+    /// Request multiple lines using the v2 API
+    ///
+    /// In contrast to `request_array()`, each line can have its own
+    /// configuration, e.g. inputs and outputs can be requested together.
+    /// Lines with flags differing from the first line need a configuration
+    /// attribute each, of which the kernel supports 10 in total, one being
+    /// used for the output values. On kernels older than 5.10, an
+    /// `Unsupported` error is returned.
+    pub fn request_lines_v2(&self, consumer: &str, lines: &[LineSettings]) -> io::Result<GpioLinesV2> {
+        let mut request: ioctl::v2::gpio_v2_line_request = unsafe { std::mem::zeroed() };
+
+        if lines.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no lines specified"));
+        }
+
+        if lines.len() > request.offsets.len() {
+            return Err(GpioError::TooManyLines.into());
+        }
+
+        for line in lines {
+            self.check_gpio(line.offset)?;
+        }
+        copy_label(&mut request.consumer, consumer)?;
+
+        let mut output_mask = 0;
+        let mut output_values = 0;
+        for (i, line) in lines.iter().enumerate() {
+            request.offsets[i] = line.offset;
+
+            if line.flags.contains(LineFlags::OUTPUT) {
+                output_mask |= 1 << i;
+                if line.value != 0 {
+                    output_values |= 1 << i;
+                }
+            }
+        }
+        request.num_lines = lines.len() as u32;
+
+        let config = &mut request.config;
+        config.flags = lines[0].flags.bits;
+
+        let mut add_attr = |id, value, mask| {
+            let index = config.num_attrs as usize;
+            if index >= config.attrs.len() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "too many different line configurations"));
+            }
+
+            config.attrs[index].attr.id = id;
+            config.attrs[index].attr.value = value;
+            config.attrs[index].mask = mask;
+            config.num_attrs += 1;
+            Ok(())
+        };
+
+        let mut configured = 0u64;
+        for (i, line) in lines.iter().enumerate() {
+            if line.flags == lines[0].flags || configured & (1 << i) != 0 {
+                continue;
+            }
+
+            let mask = lines.iter().enumerate()
+                .filter(|&(_, other)| other.flags == line.flags)
+                .fold(0, |mask, (j, _)| mask | 1 << j);
+            configured |= mask;
+
+            add_attr(ioctl::v2::GPIO_V2_LINE_ATTR_ID_FLAGS, ioctl::v2::gpio_v2_line_attribute_value { flags: line.flags.bits }, mask)?;
+        }
+
+        if output_mask != 0 {
+            add_attr(ioctl::v2::GPIO_V2_LINE_ATTR_ID_OUTPUT_VALUES, ioctl::v2::gpio_v2_line_attribute_value { values: output_values }, output_mask)?;
+        }
+
+        from_v2_result(unsafe {
+            ioctl::v2::get_line(self.file.as_raw_fd(), &mut request)
+        })?;
+
+        let offsets: Vec<u32> = lines.iter().map(|line| line.offset).collect();
+        let audit = self.audit_acquired(consumer, &offsets);
+        Ok(GpioLinesV2 {file: unsafe {std::fs::File::from_raw_fd(request.fd)}, offsets: offsets.into_boxed_slice(), consumer: consumer.to_string(), _audit: audit})
+    }
+
+    /// Request a `GpioHandle` with debouncing for a single input gpio
+    ///
+    /// The value only changes, once the line has been stable for the
+    /// `debounce` period. This requires the v2 API, so on kernels older than
+    /// 5.10 an `Unsupported` error is returned. The handle can not be
+    /// reconfigured with `set_config()`.
+    pub fn request_debounced(&self, consumer: &str, flags: RequestFlags, gpio: u32, debounce: Duration) -> io::Result<GpioHandle> {
+        let mut request: ioctl::v2::gpio_v2_line_request = unsafe { std::mem::zeroed() };
+
+        let output = RequestFlags::OUTPUT | RequestFlags::OPEN_DRAIN | RequestFlags::OPEN_SOURCE;
+        if flags.intersects(output) {
+            return Err(GpioError::ConflictingFlags { flags: flags & output, reason: "debouncing is only supported for inputs" }.into());
+        }
+
+        self.check_gpio(gpio)?;
+        check_flags(flags | RequestFlags::INPUT)?;
+        copy_label(&mut request.consumer, consumer)?;
+
+        let period = debounce.as_micros();
+        if period > u32::MAX as u128 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "debounce period too long"));
+        }
+
+        request.offsets[0] = gpio;
+        request.num_lines = 1;
+        request.config.flags = (LineFlags::INPUT | LineFlags::from(flags)).bits;
+
+        let attr = &mut request.config.attrs[0];
+        attr.attr.id = ioctl::v2::GPIO_V2_LINE_ATTR_ID_DEBOUNCE;
+        attr.attr.value.debounce_period_us = period as u32;
+        attr.mask = 1;
+        request.config.num_attrs = 1;
+
+        from_v2_result(unsafe {
+            ioctl::v2::get_line(self.file.as_raw_fd(), &mut request)
+        })?;
+
+        Ok(GpioHandle {file: unsafe {std::fs::File::from_raw_fd(request.fd)}, consumer: consumer.to_string(), flags: flags | RequestFlags::INPUT, gpio, _audit: self.audit_acquired(consumer, &[gpio]), v2: true})
+    }
+
+    /// Request a `GpioEventHandle` for a single gpio
+    pub fn request_event(&self, consumer: &str, gpio: u32, handleflags: RequestFlags, eventflags: EventRequestFlags) -> io::Result<GpioEventHandle> {
+        let mut request = ioctl::gpioevent_request { lineoffset: 0, handleflags: 0, eventflags: 0, consumer_label: [0; 32], fd: 0 };
+
+        self.check_gpio(gpio)?;
+        check_event_flags(handleflags)?;
+        copy_label(&mut request.consumer_label, consumer)?;
+
+        request.lineoffset = gpio;
+        request.handleflags = handleflags.bits;
+        request.eventflags = eventflags.bits;
+
+        from_nix_result(unsafe {
+            ioctl::get_lineevent(self.file.as_raw_fd(), &mut request)
+        })?;
+
+        Ok(GpioEventHandle {file: unsafe {std::fs::File::from_raw_fd(request.fd)}, gpio, handleflags, eventflags, last_timestamp: AtomicU64::new(0), _audit: self.audit_acquired(consumer, &[gpio]), v2: false, clock: EventClock::Monotonic, debounce: Duration::from_secs(0)})
+    }
+
+    /// Request a `GpioEventHandle` for a single gpio using the v2 API
+    fn request_event_v2(&self, consumer: &str, gpio: u32, handleflags: RequestFlags, eventflags: EventRequestFlags, debounce: Duration, clock: EventClock) -> io::Result<GpioEventHandle> {
+        let mut request: ioctl::v2::gpio_v2_line_request = unsafe { std::mem::zeroed() };
+
+        self.check_gpio(gpio)?;
+        check_event_flags(handleflags)?;
+        copy_label(&mut request.consumer, consumer)?;
+
+        request.offsets[0] = gpio;
+        request.num_lines = 1;
+        request.config = event_line_config(handleflags, eventflags, debounce, clock)?;
+
+        let res = from_v2_result(unsafe {
+            ioctl::v2::get_line(self.file.as_raw_fd(), &mut request)
+        });
+
+        match res {
+            Err(ref err) if clock_rejected(clock, err) => {
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "event clock not supported by the kernel"));
+            }
+            res => res?,
+        };
+
+        Ok(GpioEventHandle {file: unsafe {std::fs::File::from_raw_fd(request.fd)}, gpio, handleflags, eventflags, last_timestamp: AtomicU64::new(0), _audit: self.audit_acquired(consumer, &[gpio]), v2: true, clock, debounce})
+    }
+
+    /// Request a `GpioEventHandle` with debouncing for a single gpio
+    ///
+    /// The kernel only reports an edge, once the value has been stable for
+    /// the `debounce` period. This requires the v2 API, so on kernels older
+    /// than 5.10 an `Unsupported` error is returned.
+    pub fn request_event_debounced(&self, consumer: &str, gpio: u32, handleflags: RequestFlags, eventflags: EventRequestFlags, debounce: Duration) -> io::Result<GpioEventHandle> {
+        self.request_event_v2(consumer, gpio, handleflags, eventflags, debounce, EventClock::Monotonic)
+    }
+
+    /// Request a `GpioEventHandle` for a single gpio with events timestamped by `clock`
+    ///
+    /// This requires the v2 API. `EventClock::Realtime` needs kernel 5.11 and
+    /// `EventClock::Hardware` a kernel with hardware timestamp engine support
+    /// for the line. Otherwise an `Unsupported` error is returned.
+    pub fn request_event_with_clock(&self, consumer: &str, gpio: u32, handleflags: RequestFlags, eventflags: EventRequestFlags, clock: EventClock) -> io::Result<GpioEventHandle> {
+        self.request_event_v2(consumer, gpio, handleflags, eventflags, Duration::from_secs(0), clock)
+    }
+
+    /// Request a `GpioEventHandle` for a single gpio and read its initial value
+    ///
+    /// Edge detection is active before the value is read, so no edge can get
+    /// lost between both. An edge occuring in between is already reflected in
+    /// the returned value and is also queued as an event.
+    pub fn request_event_with_level(&self, consumer: &str, gpio: u32, handleflags: RequestFlags, eventflags: EventRequestFlags) -> io::Result<(GpioEventHandle, u8)> {
+        let handle = self.request_event(consumer, gpio, handleflags, eventflags)?;
+        let value = handle.get()?;
+
+        Ok((handle, value))
+    }
+
+    /// Create an `EventRequestBuilder` for requesting a `GpioEventHandle`
+    pub fn event_request(&self) -> EventRequestBuilder<'_> {
+        EventRequestBuilder {
+            chip: self,
+            consumer: String::new(),
+            gpio: None,
+            handleflags: RequestFlags::INPUT,
+            eventflags: EventRequestFlags::BOTH_EDGES,
+            debounce: Duration::from_secs(0),
+            clock: EventClock::Monotonic,
+        }
+    }
+}
+
+struct WatchdogInner {
+    handle: Option<GpioHandle>,
+    last_pet: Instant,
+    stopped: bool,
+}
+
+struct WatchdogState {
+    inner: Mutex<WatchdogInner>,
+    wakeup: Condvar,
+}
+
+/// A `GpioHandle` guarded by a software watchdog
+///
+/// A monitor thread checks, that `pet()` is called at least once per timeout.
+/// Otherwise the gpio is set to the safe value and released, after which all
+/// operations on the `WatchdogHandle` fail with `TimedOut`.
+///
+/// This is not a hardware watchdog: it only helps if the code calling `pet()`
+/// hangs while the process keeps running. If the process crashes or gets
+/// killed, the kernel releases the gpio without setting the safe value.
+pub struct WatchdogHandle {
+    state: Arc<WatchdogState>,
+    monitor: Option<thread::JoinHandle<()>>,
+}
+
+impl WatchdogHandle {
+    /// Guard `handle`, setting it to `safe_value` if not petted within `timeout`
+    pub fn new(handle: GpioHandle, safe_value: u8, timeout: Duration) -> io::Result<WatchdogHandle> {
+        let state = Arc::new(WatchdogState {
+            inner: Mutex::new(WatchdogInner {handle: Some(handle), last_pet: Instant::now(), stopped: false}),
+            wakeup: Condvar::new(),
+        });
+        let monitor_state = state.clone();
+
+        let monitor = thread::Builder::new().name("gpio-watchdog".to_string()).spawn(move || {
+            let mut inner = monitor_state.inner.lock().unwrap();
+
+            while !inner.stopped {
+                let elapsed = inner.last_pet.elapsed();
+
+                if elapsed >= timeout {
+                    if let Some(handle) = inner.handle.take() {
+                        let _ = handle.set(safe_value);
+                    }
+                    return;
+                }
+
+                inner = monitor_state.wakeup.wait_timeout(inner, timeout - elapsed).unwrap().0;
+            }
+        })?;
+
+        Ok(WatchdogHandle {state, monitor: Some(monitor)})
+    }
+
+    /// Run `f` on the guarded handle, if the watchdog did not expire
+    fn with_handle<T, F: FnOnce(&GpioHandle) -> io::Result<T>>(&self, f: F) -> io::Result<T> {
+        match self.state.inner.lock().unwrap().handle {
+            Some(ref handle) => f(handle),
+            None => Err(io::Error::new(io::ErrorKind::TimedOut, "watchdog expired")),
+        }
+    }
+
+    /// Reset the watchdog timeout
+    pub fn pet(&self) -> io::Result<()> {
+        let mut inner = self.state.inner.lock().unwrap();
+
+        if inner.handle.is_none() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "watchdog expired"));
+        }
+        inner.last_pet = Instant::now();
+
+        Ok(())
+    }
+
+    /// Check if the watchdog expired and the gpio has been released
+    pub fn is_expired(&self) -> bool {
+        self.state.inner.lock().unwrap().handle.is_none()
+    }
+
+    /// Get GPIO value
+    pub fn get(&self) -> io::Result<u8> {
+        self.with_handle(|handle| handle.get())
+    }
+
+    /// Set GPIO value
+    pub fn set(&self, value: u8) -> io::Result<()> {
+        self.with_handle(|handle| handle.set(value))
+    }
+}
+
+impl Drop for WatchdogHandle {
+    fn drop(&mut self) {
+        self.state.inner.lock().unwrap().stopped = true;
+        self.state.wakeup.notify_one();
+
+        if let Some(monitor) = self.monitor.take() {
+            let _ = monitor.join();
+        }
+    }
+}
+
+/// Status change reported by a `MonitoredChip`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MonitorStatus {
+    /// The chip has been removed
+    Disconnected,
+    /// The chip has been reopened and all events have been requested again
+    Reconnected,
+}
+
+struct MonitoredEvent {
+    consumer: String,
+    gpio: u32,
+    handleflags: RequestFlags,
+    eventflags: EventRequestFlags,
+}
+
+/// Event monitoring on a gpiochip, that recovers from removal of the device
+///
+/// When the chip disappears, e.g. because a USB GPIO adapter got unplugged,
+/// the event handles report a hangup. The `MonitoredChip` then scans the
+/// `/dev/gpiochip*` devices for a chip with the same label and amount of
+/// lines (the chip name is not stable, since the kernel may assign a new
+/// number), reopens it and requests all events again.
+pub struct MonitoredChip {
+    label: String,
+    lines: u32,
+    chip: Option<GpioChip>,
+    events: Vec<MonitoredEvent>,
+    handles: Vec<GpioEventHandle>,
+    callback: Option<Box<dyn FnMut(MonitorStatus) + Send>>,
+}
+
+impl MonitoredChip {
+    /// Interval in which a disconnected chip is searched for
+    const RECONNECT_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Start monitoring events on `chip`
+    pub fn new(chip: GpioChip) -> MonitoredChip {
+        MonitoredChip {
+            label: chip.label.clone(),
+            lines: chip.lines,
+            chip: Some(chip),
+            events: Vec::new(),
+            handles: Vec::new(),
+            callback: None,
+        }
+    }
+
+    /// Register a callback, that is called on disconnect and reconnect
+    pub fn on_status<F: FnMut(MonitorStatus) + Send + 'static>(&mut self, callback: F) {
+        self.callback = Some(Box::new(callback));
+    }
+
+    /// Check if the chip is currently available
+    pub fn is_connected(&self) -> bool {
+        self.chip.is_some()
+    }
+
+    /// Get the monitored chip, if it is currently available
+    pub fn chip(&self) -> Option<&GpioChip> {
+        self.chip.as_ref()
+    }
+
+    /// Request events for a gpio
+    ///
+    /// Returns the index, that is used to report events of this gpio.
+    pub fn request_event(&mut self, consumer: &str, gpio: u32, handleflags: RequestFlags, eventflags: EventRequestFlags) -> io::Result<usize> {
+        let handle = match self.chip {
+            Some(ref chip) => chip.request_event(consumer, gpio, handleflags, eventflags)?,
+            None => return Err(io::Error::from_raw_os_error(libc::ENODEV)),
+        };
+
+        self.handles.push(handle);
+        self.events.push(MonitoredEvent {consumer: consumer.to_string(), gpio, handleflags, eventflags});
+
+        Ok(self.events.len() - 1)
+    }
+
+    fn notify(&mut self, status: MonitorStatus) {
+        if let Some(ref mut callback) = self.callback {
+            callback(status);
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.handles.clear();
+        self.chip = None;
+        self.notify(MonitorStatus::Disconnected);
+    }
+
+    fn reconnect(&mut self) -> io::Result<bool> {
+        for path in chip_paths()? {
+            let chip = match GpioChip::new(&path) {
+                Ok(chip) => chip,
+                Err(_) => continue,
+            };
+
+            if chip.label != self.label || chip.lines != self.lines {
+                continue;
+            }
+
+            let mut handles = Vec::with_capacity(self.events.len());
+            for event in &self.events {
+                match chip.request_event(&event.consumer, event.gpio, event.handleflags, event.eventflags) {
+                    Ok(handle) => handles.push(handle),
+                    Err(_) => return Ok(false),
+                }
+            }
+
+            self.handles = handles;
+            self.chip = Some(chip);
+            self.notify(MonitorStatus::Reconnected);
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Wait for events, reconnecting to the chip if necessary
+    ///
+    /// Returns the events together with the index of their request, or an
+    /// empty list if `timeout` passed. While the chip is disconnected, it is
+    /// searched for every 500ms until the timeout passes.
+    pub fn wait(&mut self, timeout: Duration) -> io::Result<Vec<(usize, GpioEvent)>> {
+        let deadline = deadline_after(timeout);
+        let mut result = Vec::new();
+
+        loop {
+            if self.chip.is_none() && !self.reconnect()? {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining == Duration::from_secs(0) {
+                    return Ok(result);
+                }
+                thread::sleep(remaining.min(MonitoredChip::RECONNECT_INTERVAL));
+                continue;
+            }
+
+            let revents = {
+                let handles: Vec<&GpioEventHandle> = self.handles.iter().collect();
+                poll_events(&handles, poll_timeout(deadline))?
+            };
+
+            if revents.iter().any(|r| r & (libc::POLLHUP | libc::POLLERR | libc::POLLNVAL) != 0) {
+                self.disconnect();
+                continue;
+            }
+
+            for (i, r) in revents.iter().enumerate() {
+                if *r == 0 {
+                    continue;
+                }
+
+                match self.handles[i].read_all() {
+                    Ok(events) => result.extend(events.into_iter().map(|event| (i, event))),
+                    Err(ref err) if err.raw_os_error() == Some(libc::ENODEV) => {
+                        self.disconnect();
+                        break;
+                    },
+                    Err(err) => return Err(err),
+                }
+            }
+
+            if !result.is_empty() || Instant::now() >= deadline {
+                return Ok(result);
+            }
+        }
+    }
+}
+
+/// Multiple `GpioChip`s combined into a single flat numbering of gpios
+///
+/// The gpios of each chip follow the ones of the previous chip, e.g. the
+/// first gpio of the second chip has the number `chips[0].lines`.
+#[derive(Debug)]
+pub struct VirtualGpioChip {
+    chips: Vec<GpioChip>,
+
+    /// amount of gpios provided by all chips
+    pub lines: u32,
+}
+
+impl VirtualGpioChip {
+    /// Combine the provided chips in the given order
+    pub fn new(chips: Vec<GpioChip>) -> VirtualGpioChip {
+        let lines = chips.iter().map(|chip| chip.lines).sum();
+
+        VirtualGpioChip {chips, lines}
+    }
+
+    /// Get the combined chips
+    pub fn chips(&self) -> &[GpioChip] {
+        &self.chips
+    }
+
+    /// Translate a global gpio number into the chip and its local gpio number
+    pub fn locate(&self, gpio: u32) -> io::Result<(&GpioChip, u32)> {
+        let mut offset = gpio;
+
+        for chip in &self.chips {
+            if offset < chip.lines {
+                return Ok((chip, offset));
+            }
+            offset -= chip.lines;
+        }
+
+        Err(GpioError::OffsetOutOfRange { offset: gpio, lines: self.lines }.into())
+    }
+
+    /// Acquire information about a gpio
+    ///
+    /// The `gpio` field of the returned `LineInfo` contains the global number.
+    pub fn info(&self, gpio: u32) -> io::Result<LineInfo> {
+        let (chip, offset) = self.locate(gpio)?;
+        let mut info = chip.info(offset)?;
+        info.gpio = gpio;
+
+        Ok(info)
+    }
+
+    /// Request a `GpioHandle` for a single gpio
+    ///
+    /// The `gpio` field of the returned handle contains the chip local number.
+    pub fn request(&self, consumer: &str, flags: RequestFlags, gpio: u32, default: u8) -> io::Result<GpioHandle> {
+        let (chip, offset) = self.locate(gpio)?;
+        chip.request(consumer, flags, offset, default)
+    }
+
+    /// Request a `GpioEventHandle` for a single gpio
+    ///
+    /// The `gpio` field of the returned handle contains the chip local number.
+    pub fn request_event(&self, consumer: &str, gpio: u32, handleflags: RequestFlags, eventflags: EventRequestFlags) -> io::Result<GpioEventHandle> {
+        let (chip, offset) = self.locate(gpio)?;
+        chip.request_event(consumer, offset, handleflags, eventflags)
+    }
+}
+
+/// Builder for a `GpioEventHandle`, created by `GpioChip::event_request()`
+#[derive(Debug)]
+pub struct EventRequestBuilder<'a> {
+    chip: &'a GpioChip,
+    consumer: String,
+    gpio: Option<u32>,
+    handleflags: RequestFlags,
+    eventflags: EventRequestFlags,
+    debounce: Duration,
+    clock: EventClock,
+}
+
+impl<'a> EventRequestBuilder<'a> {
+    /// Set the consumer label
+    pub fn consumer(mut self, consumer: &str) -> Self {
+        self.consumer = consumer.to_string();
+        self
+    }
+
+    /// Set the gpio, that should be requested
+    pub fn line(mut self, gpio: u32) -> Self {
+        self.gpio = Some(gpio);
+        self
+    }
+
+    /// Set the handle flags (default: `RequestFlags::INPUT`)
+    pub fn handle_flags(mut self, flags: RequestFlags) -> Self {
+        self.handleflags = flags;
+        self
+    }
+
+    /// Set the edges generating events (default: `EventRequestFlags::BOTH_EDGES`)
+    pub fn edges(mut self, flags: EventRequestFlags) -> Self {
+        self.eventflags = flags;
+        self
+    }
+
+    /// Set the debounce period (default: no debouncing)
+    pub fn debounce(mut self, period: Duration) -> Self {
+        self.debounce = period;
+        self
+    }
+
+    /// Set the clock used for event timestamps (default: `EventClock::Monotonic`)
+    pub fn clock(mut self, clock: EventClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Request the `GpioEventHandle`
+    ///
+    /// Debouncing and clocks other than `EventClock::Monotonic` require the
+    /// v2 API. If the kernel does not support them, an `Unsupported` error is
+    /// returned.
+    pub fn build(self) -> io::Result<GpioEventHandle> {
+        let gpio = match self.gpio {
+            Some(gpio) => gpio,
+            None => return Err(GpioError::NoGpio.into()),
+        };
+
+        if self.debounce != Duration::from_secs(0) || self.clock != EventClock::Monotonic {
+            return self.chip.request_event_v2(&self.consumer, gpio, self.handleflags, self.eventflags, self.debounce, self.clock);
+        }
+
+        self.chip.request_event(&self.consumer, gpio, self.handleflags, self.eventflags)
+    }
+}
+
+/// Bias of a gpio requested with `LineRequest`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bias {
+    /// Enable the pull-up
+    PullUp,
+    /// Enable the pull-down
+    PullDown,
+    /// Disable pull-up and pull-down
+    Disabled,
+}
+
+/// Builder for a `GpioHandle` of a single gpio
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate gpiochip as gpio;
+///
+/// use std::time::Duration;
+///
+/// fn main() {
+///     let chip = gpio::GpioChip::new("/dev/gpiochip0").unwrap();
+///
+///     let button = gpio::LineRequest::builder()
+///         .consumer("button")
+///         .offset(4)
+///         .input()
+///         .bias(gpio::Bias::PullUp)
+///         .debounce(Duration::from_millis(10))
+///         .request(&chip)
+///         .unwrap();
+///
+///     println!("button: {}", button.get().unwrap());
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct LineRequest {
+    consumer: String,
+    gpio: Option<u32>,
+    flags: RequestFlags,
+    default: u8,
+    debounce: Duration,
+}
+
+impl LineRequest {
+    /// Create a request for an input without bias
+    pub fn builder() -> LineRequest {
+        LineRequest {
+            consumer: String::new(),
+            gpio: None,
+            flags: RequestFlags::INPUT,
+            default: 0,
+            debounce: Duration::from_secs(0),
+        }
+    }
+
+    /// Set the consumer label
+    pub fn consumer(mut self, consumer: &str) -> Self {
+        self.consumer = consumer.to_string();
+        self
+    }
+
+    /// Set the gpio, that should be requested
+    pub fn offset(mut self, gpio: u32) -> Self {
+        self.gpio = Some(gpio);
+        self
+    }
+
+    /// Request the gpio as input (default)
+    pub fn input(mut self) -> Self {
+        self.flags = (self.flags - (RequestFlags::OUTPUT | RequestFlags::OPEN_DRAIN | RequestFlags::OPEN_SOURCE)) | RequestFlags::INPUT;
+        self.default = 0;
+        self
+    }
+
+    /// Request the gpio as output driving `default`
+    pub fn output(mut self, default: u8) -> Self {
+        self.flags = (self.flags - RequestFlags::INPUT) | RequestFlags::OUTPUT;
+        self.default = default;
+        self
+    }
+
+    /// Mark the gpio as active-low
+    pub fn active_low(mut self) -> Self {
+        self.flags |= RequestFlags::ACTIVE_LOW;
+        self
+    }
+
+    /// Set the bias of the gpio
+    pub fn bias(mut self, bias: Bias) -> Self {
+        self.flags -= RequestFlags::BIAS_PULL_UP | RequestFlags::BIAS_PULL_DOWN | RequestFlags::BIAS_DISABLE;
+        self.flags |= match bias {
+            Bias::PullUp => RequestFlags::BIAS_PULL_UP,
+            Bias::PullDown => RequestFlags::BIAS_PULL_DOWN,
+            Bias::Disabled => RequestFlags::BIAS_DISABLE,
+        };
+        self
+    }
+
+    /// Debounce the input value (default: no debouncing)
+    ///
+    /// See `GpioChip::request_debounced()` for the requirements.
+    pub fn debounce(mut self, period: Duration) -> Self {
+        self.debounce = period;
+        self
+    }
+
+    /// Request the gpio from `chip`
+    pub fn request(&self, chip: &GpioChip) -> io::Result<GpioHandle> {
+        let gpio = match self.gpio {
+            Some(gpio) => gpio,
+            None => return Err(GpioError::NoGpio.into()),
+        };
+
+        if self.debounce != Duration::from_secs(0) {
+            return chip.request_debounced(&self.consumer, self.flags, gpio, self.debounce);
+        }
+
+        chip.request(&self.consumer, self.flags, gpio, self.default)
+    }
+}
+
+/// Wait until at least one gpio event has been received or timeout occured.
+///
+/// The return value is a bitmap, which marks the GpioEventHandles with data available
+pub fn wait_for_event(events: &[&GpioEventHandle], timeout_ms: i32) -> io::Result<u64> {
+    let mut result: u64 = 0;
+
+    if events.len() > 64 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Function does not support more than 64 events"))
+    }
+
+    for (i, revents) in poll_events(events, timeout_ms)?.iter().enumerate() {
+        if *revents != 0 {
+            result |= 1 << i;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Wait until at least one gpio event has been received or `timeout` expired
+///
+/// `None` blocks until an event is received. Timeouts are rounded up to full
+/// milliseconds and capped at `i32::MAX` milliseconds (about 24 days). The
+/// return value is the same bitmap as returned by `wait_for_event()`.
+pub fn wait_for_event_timeout(events: &[&GpioEventHandle], timeout: Option<Duration>) -> io::Result<u64> {
+    wait_for_event(events, duration_to_poll_timeout(timeout))
+}
+
+/// Wait until at least one gpio event has been received or `timeout` expired
+///
+/// Returns the handles with data available, in the order of `events`. The
+/// result is empty on timeout. `None` blocks until an event is received.
+/// Unlike `wait_for_event()` the number of handles is not limited.
+pub fn wait_ready<'a>(events: &[&'a GpioEventHandle], timeout: Option<Duration>) -> io::Result<Vec<&'a GpioEventHandle>> {
+    let revents = poll_events(events, duration_to_poll_timeout(timeout))?;
+
+    Ok(events.iter().zip(revents).filter(|&(_, revents)| revents != 0).map(|(&event, _)| event).collect())
+}
+
+/// Readiness of a single handle reported by `wait_for_event_detailed()`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PollResult {
+    /// Events can be read (`POLLIN`)
+    pub readable: bool,
+    /// Priority data is available (`POLLPRI`)
+    pub priority: bool,
+    /// The device has been removed (`POLLHUP`)
+    pub hangup: bool,
+    /// The fd is in an error state or invalid (`POLLERR`, `POLLNVAL`)
+    pub error: bool,
+}
+
+impl PollResult {
+    fn from_revents(revents: libc::c_short) -> PollResult {
+        PollResult {
+            readable: revents & libc::POLLIN != 0,
+            priority: revents & libc::POLLPRI != 0,
+            hangup: revents & libc::POLLHUP != 0,
+            error: revents & (libc::POLLERR | libc::POLLNVAL) != 0,
+        }
+    }
+}
+
+/// Wait like `wait_for_event_timeout()`, but report the readiness in detail
+///
+/// The result contains one entry per handle in the order of `events`, so
+/// hangups and errors can be told apart from available events. The number
+/// of handles is not limited.
+pub fn wait_for_event_detailed(events: &[&GpioEventHandle], timeout: Option<Duration>) -> io::Result<Vec<PollResult>> {
+    Ok(poll_events(events, duration_to_poll_timeout(timeout))?.into_iter().map(PollResult::from_revents).collect())
+}
+
+/// poll() the event handles and return the revents of each one
+fn poll_events(events: &[&GpioEventHandle], timeout_ms: i32) -> io::Result<Vec<libc::c_short>> {
+    let mut fds: std::vec::Vec<libc::pollfd> = Vec::with_capacity(events.len());
+
+    for event in events {
+        fds.push( libc::pollfd { fd: event.file.as_raw_fd(), events: libc::POLLIN | libc::POLLPRI, revents: 0 } );
+    }
+
+    poll_fds(&mut fds, timeout_ms)?;
+
+    Ok(fds.iter().map(|fd| fd.revents).collect())
+}
+
+/// poll() the fds, filling in their revents
+fn poll_fds(fds: &mut [libc::pollfd], timeout_ms: i32) -> io::Result<()> {
+    let ret = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout_ms) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error())
+    }
+
+    Ok(())
+}
+
+/// Interrupts `wait_for_event_cancellable()` from another thread
+///
+/// The waker is backed by an eventfd. Share it between threads using an
+/// `Arc`.
+#[derive(Debug)]
+pub struct EventWaker {
+    file: std::fs::File,
+}
+
+impl EventWaker {
+    /// Create a new waker
+    pub fn new() -> io::Result<EventWaker> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(EventWaker {file: unsafe { std::fs::File::from_raw_fd(fd) }})
+    }
+
+    /// Interrupt the running wait or, if there is none, the next one
+    pub fn wake(&self) -> io::Result<()> {
+        io::Write::write_all(&mut &self.file, &1u64.to_ne_bytes())
+    }
+
+    /// Clear pending wakeups
+    fn reset(&self) -> io::Result<()> {
+        let mut buf = [0u8; 8];
+
+        match io::Read::read(&mut &self.file, &mut buf) {
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            res => res.map(|_| ()),
+        }
+    }
+}
+
+impl AsRawFd for EventWaker {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+/// A fixed set of `GpioEventHandle`s, that can be waited for repeatedly
+///
+/// Unlike `wait_for_event()`, the poll() buffer is only allocated once, so
+/// waiting does not allocate.
+#[derive(Debug)]
+pub struct PollSet<'a> {
+    fds: Vec<libc::pollfd>,
+    handles: Vec<&'a GpioEventHandle>,
+}
+
+impl<'a> PollSet<'a> {
+    /// Create a set of up to 64 handles
+    pub fn new(events: &[&'a GpioEventHandle]) -> io::Result<PollSet<'a>> {
+        if events.len() > 64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "PollSet does not support more than 64 events"));
+        }
+
+        let fds = events.iter()
+            .map(|event| libc::pollfd { fd: event.file.as_raw_fd(), events: libc::POLLIN | libc::POLLPRI, revents: 0 })
+            .collect();
+
+        Ok(PollSet {fds, handles: events.to_vec()})
+    }
+
+    /// Get the handles in the order of the bitmap returned by `poll()`
+    pub fn handles(&self) -> &[&'a GpioEventHandle] {
+        &self.handles
+    }
+
+    /// Wait until at least one handle has data available or `timeout` passed
+    ///
+    /// Returns the same bitmap as `wait_for_event()`. `None` waits forever.
+    pub fn poll(&mut self, timeout: Option<Duration>) -> io::Result<u64> {
+        for fd in self.fds.iter_mut() {
+            fd.revents = 0;
+        }
+
+        poll_fds(&mut self.fds, duration_to_poll_timeout(timeout))?;
+
+        Ok(self.fds.iter().enumerate().fold(0, |bitmap, (i, fd)| if fd.revents != 0 { bitmap | 1 << i } else { bitmap }))
+    }
+}
+
+/// Waits for events on any number of `GpioEventHandle`s using epoll
+///
+/// Each handle is registered with a token chosen by the caller, which is
+/// returned together with the events read from the handle.
+#[derive(Debug)]
+pub struct EventWatcher<'a> {
+    epoll: std::fs::File,
+    handles: HashMap<u64, &'a GpioEventHandle>,
+}
+
+impl<'a> EventWatcher<'a> {
+    /// Maximum amount of events read from a single handle per `wait()`
+    const EVENTS_PER_HANDLE: usize = 16;
+
+    /// Create an empty watcher
+    pub fn new() -> io::Result<EventWatcher<'a>> {
+        let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(EventWatcher {epoll: unsafe { std::fs::File::from_raw_fd(fd) }, handles: HashMap::new()})
+    }
+
+    /// Watch `handle`, reporting its events with `token`
+    ///
+    /// Fails with `AlreadyExists`, if `token` is already in use.
+    pub fn add(&mut self, handle: &'a GpioEventHandle, token: u64) -> io::Result<()> {
+        if self.handles.contains_key(&token) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("token {} already in use", token)));
+        }
+
+        let mut event = libc::epoll_event { events: (libc::EPOLLIN | libc::EPOLLPRI) as u32, u64: token };
+        if unsafe { libc::epoll_ctl(self.epoll.as_raw_fd(), libc::EPOLL_CTL_ADD, handle.file.as_raw_fd(), &mut event) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        self.handles.insert(token, handle);
+        Ok(())
+    }
+
+    /// Stop watching the handle registered with `token`
+    pub fn remove(&mut self, token: u64) -> io::Result<()> {
+        let handle = match self.handles.remove(&token) {
+            Some(handle) => handle,
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, format!("unknown token {}", token))),
+        };
+
+        if unsafe { libc::epoll_ctl(self.epoll.as_raw_fd(), libc::EPOLL_CTL_DEL, handle.file.as_raw_fd(), std::ptr::null_mut()) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    /// Wait for events and read them
+    ///
+    /// Returns the events of all handles with data available together with
+    /// their token, or an empty list if `timeout` passed. `None` waits
+    /// forever. Events still queued after reading are returned by the next
+    /// call.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> io::Result<Vec<(u64, GpioEvent)>> {
+        let mut ready = vec![libc::epoll_event { events: 0, u64: 0 }; self.handles.len().max(1)];
+
+        let count = unsafe { libc::epoll_wait(self.epoll.as_raw_fd(), ready.as_mut_ptr(), ready.len() as i32, duration_to_poll_timeout(timeout)) };
+        if count < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut result = Vec::new();
+        for event in &ready[..count as usize] {
+            let token = event.u64;
+
+            if let Some(handle) = self.handles.get(&token) {
+                for event in handle.read_events(Self::EVENTS_PER_HANDLE)? {
+                    result.push((token, event));
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl<'a> AsRawFd for EventWatcher<'a> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.epoll.as_raw_fd()
+    }
+}
+
+/// Wait like `wait_for_event_timeout()`, but allow interrupting the wait
+///
+/// Returns `None` if the wait got interrupted by `waker.wake()`, otherwise
+/// the bitmap of handles with data available. Pending wakeups are cleared
+/// when they interrupt a wait.
+pub fn wait_for_event_cancellable(events: &[&GpioEventHandle], waker: &EventWaker, timeout: Option<Duration>) -> io::Result<Option<u64>> {
+    if events.len() > 64 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Function does not support more than 64 events"))
+    }
+
+    let mut fds: Vec<libc::pollfd> = events.iter()
+        .map(|event| libc::pollfd { fd: event.file.as_raw_fd(), events: libc::POLLIN | libc::POLLPRI, revents: 0 })
+        .collect();
+    fds.push(libc::pollfd { fd: waker.file.as_raw_fd(), events: libc::POLLIN, revents: 0 });
+
+    poll_fds(&mut fds, duration_to_poll_timeout(timeout))?;
+
+    if fds[events.len()].revents != 0 {
+        waker.reset()?;
+        return Ok(None);
+    }
+
+    Ok(Some(fds[..events.len()].iter().enumerate().fold(0, |bitmap, (i, fd)| if fd.revents != 0 { bitmap | 1 << i } else { bitmap })))
+}
+
+/// Wait until one of the handles reports a specific edge
+///
+/// Each condition is a handle together with the edge, that is waited for.
+/// Returns the index of the first condition, whose edge occured, or `None`
+/// if `timeout` passed. Events of other edges are read and discarded. Events
+/// queued after the matching one are not read.
+pub fn wait_for_any(conditions: &[(&GpioEventHandle, EventId)], timeout: Duration) -> io::Result<Option<usize>> {
+    let deadline = deadline_after(timeout);
+    let handles: Vec<&GpioEventHandle> = conditions.iter().map(|&(handle, _)| handle).collect();
+
+    loop {
+        let bitmap = wait_for_event(&handles, poll_timeout(deadline))?;
+        if bitmap == 0 {
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            continue;
+        }
+
+        for (i, &(handle, ref edge)) in conditions.iter().enumerate() {
+            if bitmap & (1 << i) != 0 && handle.read()?.id == *edge {
+                return Ok(Some(i));
+            }
+        }
+    }
+}
+
+/// Get the values of multiple `GpioHandle`s
+///
+/// The handles are read one after another, so the values are not sampled at
+/// the same time. Request the gpios with `GpioChip::request_array()` to read
+/// them atomically.
+pub fn get_many(handles: &[&GpioHandle]) -> io::Result<Vec<u8>> {
+    handles.iter().map(|handle| handle.get()).collect()
+}
+
+/// Read all queued events from every handle with data available
+///
+/// Typically called after `wait_for_event()` signaled data. The returned
+/// list contains the index and the events of each handle, that had events
+/// queued.
+pub fn drain_all(events: &[&GpioEventHandle]) -> io::Result<Vec<(usize, Vec<GpioEvent>)>> {
+    let bitmap = wait_for_event(events, 0)?;
+    let mut result = Vec::new();
 
-        while bitmap != 0 {
-            try!(self.read());
-            bitmap = try!(wait_for_event(&[&self], 0));
+    for (i, event) in events.iter().enumerate() {
+        if bitmap & (1 << i) != 0 {
+            result.push((i, event.read_all()?));
         }
-
-        Ok(())
     }
 
-    /// Get GPIO value
-    pub fn get(&self) -> io::Result<u8> {
-        let mut data = ioctl::gpiohandle_data { values: [0; 64] };
+    Ok(result)
+}
 
-        try!(from_nix_result(unsafe {
-            ioctl::get_line_values(self.file.as_raw_fd(), &mut data)
-        }));
+/// Collect events from all handles until `max_latency` passed
+///
+/// The deadline is computed once, and every `wait_for_event()` call only
+/// waits for the time remaining until it, so no event stays in the batch for
+/// longer than `max_latency` (plus the time needed to read it). A longer
+/// latency results in bigger batches and less wakeups, a shorter one in more
+/// responsive, but less efficient processing. The returned list contains the
+/// index of the handle together with each event.
+pub fn read_batch(events: &[&GpioEventHandle], max_latency: Duration) -> io::Result<Vec<(usize, GpioEvent)>> {
+    let deadline = deadline_after(max_latency);
+    let mut result = Vec::new();
 
-        Ok(data.values[0])
+    loop {
+        let bitmap = wait_for_event(events, poll_timeout(deadline))?;
+
+        for (i, event) in events.iter().enumerate() {
+            if bitmap & (1 << i) != 0 {
+                result.extend(event.read_all()?.into_iter().map(|e| (i, e)));
+            }
+        }
+
+        if Instant::now() >= deadline {
+            return Ok(result);
+        }
     }
 }
 
-impl GpioHandle {
-    /// Get GPIO value
-    pub fn get(&self) -> io::Result<u8> {
-        let mut data = ioctl::gpiohandle_data { values: [0; 64] };
-
-        try!(from_nix_result(unsafe {
-            ioctl::get_line_values(self.file.as_raw_fd(), &mut data)
-        }));
+/// Measure the time until a change of `out` is seen on `sense`
+///
+/// Stale events are flushed from `sense`, then `out` is set to `target` and
+/// the time between setting the value and the matching edge on `sense` is
+/// returned. The end point is the kernel timestamp of the event, so the
+/// result does not include the latency of reading the event. The start
+/// point is read from the clock of `sense`, which must not be
+/// `EventClock::Hardware`, otherwise `InvalidInput` is returned. If no
+/// matching edge is seen within `timeout`, `TimedOut` is returned.
+pub fn measure_settling(out: &GpioHandle, sense: &GpioEventHandle, target: u8, timeout: Duration) -> io::Result<Duration> {
+    let edge = if target != 0 { EventId::RISING_EDGE } else { EventId::FALLING_EDGE };
 
-        Ok(data.values[0])
+    if sense.clock() == EventClock::Hardware {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "hardware timestamps can not be compared to a system clock"));
     }
 
-    /// Set GPIO value
-    pub fn set(&self, value: u8) -> io::Result<()> {
-        let mut data = ioctl::gpiohandle_data { values: [0; 64] };
-        data.values[0] = value;
+    sense.flush()?;
 
-        try!(from_nix_result(unsafe {
-            ioctl::set_line_values(self.file.as_raw_fd(), &mut data)
-        }));
+    let deadline = deadline_after(timeout);
+    let start = clock_now(sense.clock())?.unwrap_or(0);
+    out.set(target)?;
 
-        Ok(())
+    loop {
+        let event = sense.read_before(deadline)?;
+
+        if event.id == edge {
+            return Ok(Duration::from_nanos(event.timestamp.saturating_sub(start)));
+        }
     }
 }
 
-impl GpioArrayHandle {
-    /// Get GPIO values
-    pub fn get(&self) -> io::Result<([u8; 64])> {
-        let mut data = ioctl::gpiohandle_data { values: [0; 64] };
-
-        try!(from_nix_result(unsafe {
-            ioctl::get_line_values(self.file.as_raw_fd(), &mut data)
-        }));
+/// A `Stream` of events received from a `GpioEventHandle`
+///
+/// The fd is registered with the `async-io` reactor, that drives async-std,
+/// so the stream can be awaited from async-std tasks. The first error ends
+/// the stream after it has been yielded.
+#[cfg(feature = "async-std")]
+#[derive(Debug)]
+pub struct GpioEventStream {
+    handle: async_io::Async<GpioEventHandle>,
+    failed: bool,
+}
 
-        Ok(data.values)
+#[cfg(feature = "async-std")]
+impl GpioEventHandle {
+    /// Convert the handle into a `GpioEventStream`
+    ///
+    /// This switches the fd into non-blocking mode.
+    pub fn into_stream(self) -> io::Result<GpioEventStream> {
+        Ok(GpioEventStream {handle: async_io::Async::new(self)?, failed: false})
     }
+}
 
-    /// Set GPIO values
-    pub fn set(&self, values: &[u8]) -> io::Result<()> {
-        let mut data = ioctl::gpiohandle_data { values: [0; 64] };
+#[cfg(feature = "async-std")]
+impl futures_core::Stream for GpioEventStream {
+    type Item = io::Result<GpioEvent>;
 
-        if values.len() != self.gpios.len() || values.len() > 64 {
-            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid amount of values"));
+    fn poll_next(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context) -> std::task::Poll<Option<Self::Item>> {
+        if self.failed {
+            return std::task::Poll::Ready(None);
         }
 
-        for i in 0..values.len() {
-            data.values[i] = values[i];
-        }
+        let res = loop {
+            match self.handle.get_ref().read() {
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {},
+                res => break res,
+            }
 
-        try!(from_nix_result(unsafe {
-            ioctl::set_line_values(self.file.as_raw_fd(), &mut data)
-        }));
+            match self.handle.poll_readable(cx) {
+                std::task::Poll::Ready(Ok(())) => {},
+                std::task::Poll::Ready(Err(err)) => break Err(err),
+                std::task::Poll::Pending => return std::task::Poll::Pending,
+            }
+        };
 
-        Ok(())
+        self.failed = res.is_err();
+        std::task::Poll::Ready(Some(res))
     }
 }
 
-impl GpioChip {
-    /// Acquire information about the gpiochip
+/// A `GpioEventHandle` registered with the tokio reactor
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate gpiochip as gpio;
+/// extern crate tokio;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let chip = gpio::GpioChip::new("/dev/gpiochip0").unwrap();
+///     let button = chip.request_event("button", 0, gpio::RequestFlags::INPUT, gpio::EventRequestFlags::BOTH_EDGES).unwrap().into_async().unwrap();
+///     let sensor = chip.request_event("sensor", 1, gpio::RequestFlags::INPUT, gpio::EventRequestFlags::RISING_EDGE).unwrap().into_async().unwrap();
+///
+///     loop {
+///         tokio::select! {
+///             event = button.read_event() => println!("button: {:?}", event.unwrap().id),
+///             event = sensor.read_event() => println!("sensor: {:?}", event.unwrap().id),
+///         }
+///     }
+/// }
+/// ```
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct AsyncGpioEventHandle {
+    handle: tokio::io::unix::AsyncFd<GpioEventHandle>,
+}
+
+#[cfg(feature = "tokio")]
+impl GpioEventHandle {
+    /// Convert the handle into an `AsyncGpioEventHandle`
     ///
-    /// Returns an Error or (name, label, number_of_gpios)
-    fn chipinfo(fd: RawFd) -> io::Result<(String, String, u32)> {
-        let mut info = ioctl::gpiochip_info { name: [0; 32], label: [0; 32], lines: 0 };
+    /// This switches the fd into non-blocking mode.
+    pub fn into_async(self) -> io::Result<AsyncGpioEventHandle> {
+        set_fd_nonblocking(self.file.as_raw_fd(), true)?;
+        Ok(AsyncGpioEventHandle {handle: tokio::io::unix::AsyncFd::new(self)?})
+    }
+}
 
-        try!(from_nix_result(unsafe {
-            ioctl::get_chipinfo(fd, &mut info)
-        }));
+#[cfg(feature = "tokio")]
+impl AsyncGpioEventHandle {
+    /// Wait for the next event and read it
+    ///
+    /// This is cancel safe, so it can be used in `tokio::select!`.
+    pub async fn read_event(&self) -> io::Result<GpioEvent> {
+        loop {
+            let mut guard = self.handle.readable().await?;
 
-        let name = unsafe {CStr::from_ptr(info.name.as_ptr())}.to_string_lossy().into_owned();
-        let label = unsafe {CStr::from_ptr(info.label.as_ptr())}.to_string_lossy().into_owned();
+            match guard.try_io(|handle| handle.get_ref().read()) {
+                Ok(res) => return res,
+                Err(_would_block) => continue,
+            }
+        }
+    }
 
-        Ok((name, label, info.lines))
+    /// Get a reference to the wrapped `GpioEventHandle`
+    pub fn get_ref(&self) -> &GpioEventHandle {
+        self.handle.get_ref()
     }
 
-    /// Open the gpiochip with the provided path
+    /// Unregister from the reactor and return the wrapped `GpioEventHandle`
     ///
-    /// Typically, the path will be something like `"/dev/gpiochip0"`.
-    pub fn new<P: AsRef<std::path::Path>>(path: P) -> io::Result<GpioChip> {
-        let file = try!(std::fs::File::open(path));
-        let (name, label, lines) = try!(GpioChip::chipinfo(file.as_raw_fd()));
-
-        Ok(GpioChip {file: file, name: name, label: label, lines: lines})
+    /// The fd stays in non-blocking mode.
+    pub fn into_inner(self) -> GpioEventHandle {
+        self.handle.into_inner()
     }
 
-    /// Acquire information about a gpio
-    pub fn info(&self, gpio: u32) -> io::Result<(LineInfo)> {
-        let mut info = ioctl::gpioline_info { line_offset: 0, flags: 0, name: [0; 32], consumer: [0; 32] };
-        info.line_offset = gpio;
+    /// Convert the handle into a `Stream` of events
+    pub fn into_stream(self) -> AsyncGpioEventStream {
+        AsyncGpioEventStream {handle: self, failed: false}
+    }
+}
 
-        try!(from_nix_result(unsafe {
-            ioctl::get_lineinfo(self.file.as_raw_fd(), &mut info)
-        }));
+/// A `Stream` of events received from an `AsyncGpioEventHandle`
+///
+/// Events are only read from the fd when the stream is polled, so unread
+/// events queue up in the kernel. The first error ends the stream after it
+/// has been yielded.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct AsyncGpioEventStream {
+    handle: AsyncGpioEventHandle,
+    failed: bool,
+}
 
-        let name = unsafe {CStr::from_ptr(info.name.as_ptr())}.to_string_lossy().into_owned();
-        let consumer = unsafe {CStr::from_ptr(info.consumer.as_ptr())}.to_string_lossy().into_owned();
-        let flags = Flags { bits: info.flags, };
-        Ok(LineInfo {gpio: gpio, name: name, consumer: consumer, flags: flags})
-    }
+#[cfg(feature = "tokio")]
+impl futures_core::Stream for AsyncGpioEventStream {
+    type Item = io::Result<GpioEvent>;
 
-    /// Request a `GpioHandle` for a single gpio
-    pub fn request(&self, consumer: &str, flags: RequestFlags, gpio: u32, default: u8) -> io::Result<(GpioHandle)> {
-        let mut request = ioctl::gpiohandle_request { lineoffsets: [0; 64], flags: 0, default_values: [0; 64], consumer_label: [0; 32], lines: 0, fd: 0 };
+    fn poll_next(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context) -> std::task::Poll<Option<Self::Item>> {
+        if self.failed {
+            return std::task::Poll::Ready(None);
+        }
 
-        request.lineoffsets[0] = gpio;
-        request.flags = flags.bits;
-        request.default_values[0] = default;
-        request.lines = 1;
+        let res = loop {
+            let mut guard = match self.handle.handle.poll_read_ready(cx) {
+                std::task::Poll::Ready(Ok(guard)) => guard,
+                std::task::Poll::Ready(Err(err)) => break Err(err),
+                std::task::Poll::Pending => return std::task::Poll::Pending,
+            };
 
-        for i in 0..request.consumer_label.len() {
-            if i >= consumer.len() {
-                break;
+            match guard.try_io(|handle| handle.get_ref().read()) {
+                Ok(res) => break res,
+                Err(_would_block) => continue,
             }
-            request.consumer_label[i] = consumer.as_bytes()[i] as std::os::raw::c_char;
-        }
+        };
 
-        try!(from_nix_result(unsafe {
-            ioctl::get_linehandle(self.file.as_raw_fd(), &mut request)
-        }));
+        self.failed = res.is_err();
+        std::task::Poll::Ready(Some(res))
+    }
+}
 
-        Ok(GpioHandle {file: unsafe {std::fs::File::from_raw_fd(request.fd)}, consumer: consumer.to_string(), flags: flags, gpio: gpio})
+/// Allows registering the event handle with a `mio::Poll`
+///
+/// The handle only becomes readable, so register it with `Interest::READABLE`.
+/// Registering switches the fd into non-blocking mode. As mio is edge
+/// triggered, drain the handle with `read_events()` until it returns no
+/// events after each wakeup.
+#[cfg(feature = "mio")]
+impl mio::event::Source for GpioEventHandle {
+    fn register(&mut self, registry: &mio::Registry, token: mio::Token, interests: mio::Interest) -> io::Result<()> {
+        set_fd_nonblocking(self.file.as_raw_fd(), true)?;
+        mio::unix::SourceFd(&self.file.as_raw_fd()).register(registry, token, interests)
     }
 
-    /// Request a `GpioArrayHandle` for multiple gpios, that should be get/set simultaneously
-    pub fn request_array(&self, consumer: &str, flags: RequestFlags, gpios: &[u32], default_values: &[u8]) -> io::Result<(GpioArrayHandle)> {
-        let mut request = ioctl::gpiohandle_request { lineoffsets: [0; 64], flags: 0, default_values: [0; 64], consumer_label: [0; 32], lines: 0, fd: 0 };
-        let mut vec: std::vec::Vec<u32> = std::vec::Vec::with_capacity(gpios.len());
+    fn reregister(&mut self, registry: &mio::Registry, token: mio::Token, interests: mio::Interest) -> io::Result<()> {
+        mio::unix::SourceFd(&self.file.as_raw_fd()).reregister(registry, token, interests)
+    }
 
-        if gpios.len() > request.lineoffsets.len() {
-            io::Error::new(io::ErrorKind::InvalidInput, "array to big");
-        }
+    fn deregister(&mut self, registry: &mio::Registry) -> io::Result<()> {
+        mio::unix::SourceFd(&self.file.as_raw_fd()).deregister(registry)
+    }
+}
 
-        if gpios.len() != default_values.len() {
-            io::Error::new(io::ErrorKind::InvalidInput, "number of default values does not match number of gpios");
-        }
+/// Reports the logical line value
+///
+/// Active-low lines are inverted by the kernel, so `is_high()` is true for an
+/// asserted line, independent of the physical level.
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::digital::v2::InputPin for GpioHandle {
+    type Error = io::Error;
 
-        request.flags = flags.bits;
-        request.lines = gpios.len() as u32;
-        for i in 0..request.consumer_label.len() {
-            if i >= consumer.len() {
-                break;
-            }
-            request.consumer_label[i] = consumer.as_bytes()[i] as std::os::raw::c_char;
-        }
+    fn is_high(&self) -> io::Result<bool> {
+        Ok(self.get()? == 1)
+    }
 
-        for x in 0..gpios.len() {
-            request.lineoffsets[x] = gpios[x];
-            request.default_values[x] = default_values[x];
-            vec.push(gpios[x]);
-        }
+    fn is_low(&self) -> io::Result<bool> {
+        Ok(self.get()? == 0)
+    }
+}
 
-        try!(from_nix_result(unsafe {
-            ioctl::get_linehandle(self.file.as_raw_fd(), &mut request)
-        }));
+/// Sets the logical line value, see `InputPin` regarding active-low lines
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::digital::v2::OutputPin for GpioHandle {
+    type Error = io::Error;
 
-        Ok(GpioArrayHandle {file: unsafe {std::fs::File::from_raw_fd(request.fd)}, consumer: consumer.to_string(), flags: flags, gpios: vec.into_boxed_slice()})
+    fn set_low(&mut self) -> io::Result<()> {
+        self.set(0)
     }
 
-    /// Request a `GpioEventHandle` for a single gpio
-    pub fn request_event(&self, consumer: &str, gpio: u32, handleflags: RequestFlags, eventflags: EventRequestFlags) -> io::Result<(GpioEventHandle)> {
-        let mut request = ioctl::gpioevent_request { lineoffset: 0, handleflags: 0, eventflags: 0, consumer_label: [0; 32], fd: 0 };
+    fn set_high(&mut self) -> io::Result<()> {
+        self.set(1)
+    }
+}
 
-        for i in 0..request.consumer_label.len() {
-            if i >= consumer.len() {
-                break;
-            }
-            request.consumer_label[i] = consumer.as_bytes()[i] as std::os::raw::c_char;
+/// Summarizes the chip, e.g. `gpiochip0 "pinctrl-bcm2835" (54 lines)`
+impl std::fmt::Display for GpioChip {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {:?} ({} lines)", self.name, self.label, self.lines)
+    }
+}
+
+/// Formats the line as `gpio: name [flags] consumer`
+///
+/// Unnamed lines and lines without consumer are printed as `unnamed` and
+/// `unused`, e.g. `17: "led0" [OUTPUT, ACTIVE_LOW] "heartbeat"`.
+impl std::fmt::Display for LineInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: ", self.gpio)?;
+
+        match self.name {
+            Some(ref name) => write!(f, "{:?}", name)?,
+            None => write!(f, "unnamed")?,
         }
 
-        request.lineoffset = gpio;
-        request.handleflags = handleflags.bits;
-        request.eventflags = eventflags.bits;
+        write!(f, " [{}] ", self.flags.names().join(", "))?;
 
-        try!(from_nix_result(unsafe {
-            ioctl::get_lineevent(self.file.as_raw_fd(), &mut request)
-        }));
+        match self.consumer {
+            Some(ref consumer) => write!(f, "{:?}", consumer),
+            None => write!(f, "unused"),
+        }
+    }
+}
 
-        Ok(GpioEventHandle {file: unsafe {std::fs::File::from_raw_fd(request.fd)}, gpio: gpio, handleflags: handleflags, eventflags: eventflags})
+impl std::fmt::Debug for GpioChip {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("GpioChip")
+            .field("fd", &self.file.as_raw_fd())
+            .field("name", &self.name)
+            .field("label", &self.label)
+            .field("lines", &self.lines)
+            .finish()
     }
 }
 
-/// Wait until at least one gpio event has been received or timeout occured.
-///
-/// The return value is a bitmap, which marks the GpioEventHandles with data available
-pub fn wait_for_event(events: &[&GpioEventHandle], timeout_ms: i32) -> io::Result<(u64)> {
-    let mut fds: std::vec::Vec<libc::pollfd> = Vec::with_capacity(events.len());
-    let mut result: u64 = 0;
+impl std::fmt::Debug for GpioHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("GpioHandle")
+            .field("fd", &self.file.as_raw_fd())
+            .field("gpio", &self.gpio)
+            .field("consumer", &self.consumer)
+            .field("flags", &self.flags)
+            .finish()
+    }
+}
 
-    if events.len() > 64 {
-        return Err(io::Error::new(io::ErrorKind::InvalidData, "Function does not support more than 64 events"))
+impl std::fmt::Debug for GpioArrayHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("GpioArrayHandle")
+            .field("fd", &self.file.as_raw_fd())
+            .field("gpios", &self.gpios)
+            .field("consumer", &self.consumer)
+            .field("flags", &self.flags)
+            .finish()
     }
+}
 
-    for event in events {
-        fds.push( libc::pollfd { fd: event.file.as_raw_fd(), events: libc::POLLIN | libc::POLLPRI, revents: 0 } );
+impl std::fmt::Debug for GpioLinesV2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("GpioLinesV2")
+            .field("fd", &self.file.as_raw_fd())
+            .field("offsets", &self.offsets)
+            .field("consumer", &self.consumer)
+            .finish()
     }
+}
 
-    let ret = unsafe { libc::poll(&mut fds[0], fds.len() as libc::nfds_t, timeout_ms) };
-    if ret < 0 {
-        return Err(io::Error::last_os_error())
-    } else if ret == 0 {
-        return Ok(0);
+impl std::fmt::Debug for GpioEventHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("GpioEventHandle")
+            .field("fd", &self.file.as_raw_fd())
+            .field("gpio", &self.gpio)
+            .field("eventflags", &self.eventflags)
+            .field("handleflags", &self.handleflags)
+            .finish()
     }
+}
 
-    for i in 0..fds.len() {
-        if fds[i].revents != 0 {
-            result |= 1 << i;
+/// Reads raw event records
+///
+/// The records are `gpioevent_data` structs for handles requested with the
+/// v1 API and `gpio_v2_line_event` structs for the v2 API. Only whole records
+/// are read, so a buffer smaller than one record is rejected.
+impl io::Read for &GpioEventHandle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.len() < self.record_size() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "buffer smaller than an event record"));
         }
+
+        io::Read::read(&mut &self.file, buf)
     }
+}
 
-    Ok(result)
+/// See the implementation for `&GpioEventHandle`
+impl io::Read for GpioEventHandle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::Read::read(&mut &*self, buf)
+    }
 }
 
+/// Panics, if `fd` is not a gpiochip. Use `GpioChip::from_fd()` to handle
+/// this case.
 impl FromRawFd for GpioChip {
     unsafe fn from_raw_fd(fd: RawFd) -> GpioChip {
-        let file = std::fs::File::from_raw_fd(fd);
-        let (name, label, lines) = GpioChip::chipinfo(fd).unwrap();
-        GpioChip { file: file, name: name, label: label, lines: lines }
+        GpioChip::from_fd(OwnedFd::from_raw_fd(fd)).expect("fd is not a gpiochip")
     }
 }
 
@@ -538,6 +4427,7 @@ impl AsRawFd for GpioChip {
 
 impl IntoRawFd for GpioHandle {
     fn into_raw_fd(self) -> RawFd {
+        detach_audit(&self._audit);
         self.file.into_raw_fd()
     }
 }
@@ -550,6 +4440,7 @@ impl AsRawFd for GpioHandle {
 
 impl IntoRawFd for GpioArrayHandle {
     fn into_raw_fd(self) -> RawFd {
+        detach_audit(&self._audit);
         self.file.into_raw_fd()
     }
 }
@@ -562,6 +4453,7 @@ impl AsRawFd for GpioArrayHandle {
 
 impl IntoRawFd for GpioEventHandle {
     fn into_raw_fd(self) -> RawFd {
+        detach_audit(&self._audit);
         self.file.into_raw_fd()
     }
 }
@@ -571,3 +4463,250 @@ impl AsRawFd for GpioEventHandle {
         self.file.as_raw_fd()
     }
 }
+
+impl IntoRawFd for GpioLinesV2 {
+    fn into_raw_fd(self) -> RawFd {
+        detach_audit(&self._audit);
+        self.file.into_raw_fd()
+    }
+}
+
+impl AsRawFd for GpioLinesV2 {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl AsFd for GpioChip {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl From<GpioChip> for OwnedFd {
+    fn from(chip: GpioChip) -> OwnedFd {
+        chip.file.into()
+    }
+}
+
+impl AsFd for GpioHandle {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl From<GpioHandle> for OwnedFd {
+    fn from(handle: GpioHandle) -> OwnedFd {
+        detach_audit(&handle._audit);
+        handle.file.into()
+    }
+}
+
+impl AsFd for GpioArrayHandle {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl From<GpioArrayHandle> for OwnedFd {
+    fn from(handle: GpioArrayHandle) -> OwnedFd {
+        detach_audit(&handle._audit);
+        handle.file.into()
+    }
+}
+
+impl AsFd for GpioEventHandle {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl From<GpioEventHandle> for OwnedFd {
+    fn from(handle: GpioEventHandle) -> OwnedFd {
+        detach_audit(&handle._audit);
+        handle.file.into()
+    }
+}
+
+impl AsFd for GpioLinesV2 {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl From<GpioLinesV2> for OwnedFd {
+    fn from(lines: GpioLinesV2) -> OwnedFd {
+        detach_audit(&lines._audit);
+        lines.file.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(buf: &[std::os::raw::c_char; 32]) -> String {
+        unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy().into_owned()
+    }
+
+    /// Chip backed by `/dev/null`, so any ioctl reaching it fails with ENOTTY
+    fn null_chip() -> GpioChip {
+        GpioChip {
+            file: std::fs::File::open("/dev/null").unwrap(),
+            name: "null".to_string(),
+            label: "null".to_string(),
+            lines: 8,
+            audit: None,
+        }
+    }
+
+    #[test]
+    fn copy_label_rejects_non_ascii() {
+        let mut buf = [0; 32];
+        let err = copy_label(&mut buf, "led \u{1f4a1}").unwrap_err();
+        assert!(matches!(err, GpioError::InvalidLabel));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn copy_label_rejects_nul() {
+        let mut buf = [0; 32];
+        let err = copy_label(&mut buf, "a\0b").unwrap_err();
+        assert!(matches!(err, GpioError::InvalidLabel));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn copy_label_accepts_31_bytes() {
+        let mut buf = [0x55; 32];
+        let text = "a".repeat(31);
+        copy_label(&mut buf, &text).unwrap();
+        assert_eq!(buf[31], 0);
+        assert_eq!(label(&buf), text);
+    }
+
+    #[test]
+    fn copy_label_rejects_32_bytes() {
+        let mut buf = [0; 32];
+        let err = copy_label(&mut buf, &"a".repeat(32)).unwrap_err();
+        assert!(matches!(err, GpioError::LabelTooLong { len: 32 }));
+    }
+
+    #[test]
+    fn copy_label_terminates_shorter_label() {
+        let mut buf = [0x55; 32];
+        copy_label(&mut buf, "button").unwrap();
+        assert_eq!(buf[6], 0);
+        assert_eq!(label(&buf), "button");
+    }
+
+    #[test]
+    fn request_rejects_long_consumer() {
+        let chip = null_chip();
+        let err = chip.request(&"c".repeat(40), RequestFlags::INPUT, 0, 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(matches!(GpioError::from(err), GpioError::LabelTooLong { len: 40 }));
+    }
+
+    #[test]
+    fn request_array_rejects_65_gpios() {
+        let chip = null_chip();
+        let gpios = [0; 65];
+        let err = chip.request_array("array", RequestFlags::INPUT, &gpios, &[0; 65]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn request_array_rejects_mismatched_defaults() {
+        let chip = null_chip();
+        let err = chip.request_array("array", RequestFlags::OUTPUT, &[0, 1, 2], &[0, 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(matches!(GpioError::from(err), GpioError::ValueCountMismatch { expected: 3, got: 2 }));
+    }
+
+    #[test]
+    fn majority_vote_picks_majority() {
+        assert_eq!(majority_vote(5, 4, 3).unwrap(), 1);
+        assert_eq!(majority_vote(5, 1, 3).unwrap(), 0);
+        assert_eq!(majority_vote(5, 5, 5).unwrap(), 1);
+    }
+
+    #[test]
+    fn majority_vote_rejects_tie() {
+        let err = majority_vote(4, 2, 2).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn majority_vote_requires_agree() {
+        let err = majority_vote(5, 3, 4).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(majority_vote(5, 3, 3).unwrap(), 1);
+        assert_eq!(majority_vote(5, 2, 3).unwrap(), 0);
+    }
+
+    #[test]
+    fn open_sets_cloexec() {
+        let file = open_cloexec(std::path::Path::new("/dev/null")).unwrap();
+        let flags = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETFD) };
+        assert!(flags >= 0);
+        assert_ne!(flags & libc::FD_CLOEXEC, 0);
+    }
+
+    #[test]
+    fn open_is_not_inherited() {
+        let path = std::env::temp_dir().join(format!("gpiochip-cloexec-{}", std::process::id()));
+        std::fs::write(&path, b"").unwrap();
+        let file = open_cloexec(&path).unwrap();
+        let output = std::process::Command::new("ls").arg("-l").arg("/proc/self/fd").output();
+        std::fs::remove_file(&path).unwrap();
+        let output = output.unwrap();
+        assert!(output.status.success());
+        assert!(!String::from_utf8_lossy(&output.stdout).contains(path.to_str().unwrap()));
+        drop(file);
+    }
+
+    #[test]
+    fn direction_flags_to_output() {
+        let flags = direction_flags(RequestFlags::INPUT | RequestFlags::ACTIVE_LOW, true);
+        assert_eq!(flags, RequestFlags::OUTPUT | RequestFlags::ACTIVE_LOW);
+    }
+
+    #[test]
+    fn direction_flags_to_input() {
+        let flags = direction_flags(RequestFlags::OUTPUT | RequestFlags::OPEN_DRAIN | RequestFlags::ACTIVE_LOW, false);
+        assert_eq!(flags, RequestFlags::INPUT | RequestFlags::ACTIVE_LOW);
+
+        let flags = direction_flags(RequestFlags::OUTPUT | RequestFlags::OPEN_SOURCE, false);
+        assert_eq!(flags, RequestFlags::INPUT);
+    }
+
+    #[test]
+    fn direction_flags_keeps_open_drain_for_output() {
+        let flags = direction_flags(RequestFlags::OUTPUT | RequestFlags::OPEN_DRAIN, true);
+        assert_eq!(flags, RequestFlags::OUTPUT | RequestFlags::OPEN_DRAIN);
+    }
+
+    #[test]
+    fn check_value_rejects_2() {
+        assert!(check_value(1).is_ok());
+        assert!(matches!(check_value(2), Err(GpioError::InvalidValue { value: 2 })));
+    }
+
+    #[test]
+    fn request_rejects_invalid_default() {
+        let chip = null_chip();
+        let err = chip.request("c", RequestFlags::OUTPUT, 0, 2).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(matches!(GpioError::from(err), GpioError::InvalidValue { value: 2 }));
+    }
+
+    #[test]
+    fn smart_flags_override_inference() {
+        assert_eq!(smart_flags(RequestFlags::INPUT, "RESET_N", None), RequestFlags::INPUT | RequestFlags::ACTIVE_LOW);
+        assert_eq!(smart_flags(RequestFlags::INPUT, "RESET_N", Some(false)), RequestFlags::INPUT);
+        assert_eq!(smart_flags(RequestFlags::INPUT | RequestFlags::ACTIVE_LOW, "LED", Some(false)), RequestFlags::INPUT);
+        assert_eq!(smart_flags(RequestFlags::INPUT, "LED", Some(true)), RequestFlags::INPUT | RequestFlags::ACTIVE_LOW);
+        assert_eq!(smart_flags(RequestFlags::INPUT | RequestFlags::ACTIVE_LOW, "LED", None), RequestFlags::INPUT | RequestFlags::ACTIVE_LOW);
+    }
+}